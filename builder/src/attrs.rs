@@ -0,0 +1,783 @@
+use crate::errors::Errors;
+use crate::naming::closest;
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    parenthesized, token, Attribute, Expr, ExprLit, Ident, Lit, LitStr, Meta, MetaList,
+    MetaNameValue, NestedMeta, Path, Token, Type, Visibility,
+};
+
+pub(crate) fn compare_path_with_str(p: &Path, s: &str) -> bool {
+    let parts = p
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .collect::<Vec<_>>();
+    parts.join("::") == s
+}
+
+pub(crate) fn find_builder_attrs(attrs: &[Attribute]) -> Vec<&Attribute> {
+    attrs
+        .iter()
+        .filter(|a| {
+            let segments = &a.path.segments;
+            match segments.len() {
+                1 => {
+                    let ai = &segments[0].ident;
+                    ai == "builder"
+                }
+                _ => false,
+            }
+        })
+        .collect::<Vec<_>>()
+}
+
+/// Options of a field's `#[builder(...)]`, and how each is given.
+const FIELD_OPTIONS: &[(&str, &str)] = &[
+    ("each", "`each = \"...\"` or `each(name = \"...\")`"),
+    ("name", "`name = \"...\"`"),
+    ("default", "`default` or `default = \"...\"`"),
+    ("setter", "`setter(...)`"),
+    ("optional", "`optional`"),
+    ("skip", "`skip`"),
+    ("sub_builder", "`sub_builder`"),
+    ("merge", "`merge = \"...\"`"),
+    ("env", "`env = \"...\"`"),
+    ("field_attr", "`field_attr(...)`"),
+];
+
+/// Options of a struct's `#[builder(...)]`, and how each is given.
+const STRUCT_OPTIONS: &[(&str, &str)] = &[
+    ("typestate", "`typestate`"),
+    ("default", "`default`"),
+    ("no_std", "`no_std`"),
+    ("deserialize", "`deserialize`"),
+    ("setter", "`setter(...)`"),
+    ("error", "`error = \"...\"`"),
+    ("pattern", "`pattern = \"...\"`"),
+    ("validate", "`validate = \"...\"`"),
+    ("env_prefix", "`env_prefix = \"...\"`"),
+    ("name", "`name = \"...\"`"),
+    ("vis", "`vis = \"...\"`"),
+    ("setter_prefix", "`setter_prefix = \"...\"`"),
+    ("constructor", "`constructor = \"...\"`"),
+    ("build_fn", "`build_fn = \"...\"`"),
+    ("derive", "`derive(...)`"),
+    ("struct_attr", "`struct_attr(...)`"),
+];
+
+/// Options of `setter(...)`, and how each is given.
+const SETTER_OPTIONS: &[(&str, &str)] = &[
+    ("into", "`into`"),
+    ("try_into", "`try_into`"),
+    ("fallible", "`fallible`"),
+    ("with", "`with = ...`"),
+];
+
+/// Options that add to what they were given before rather than replacing it.
+const REPEATABLE_OPTIONS: &[&str] = &["derive", "struct_attr", "field_attr"];
+
+/// Name of the option `n`, if it has one.
+fn option_name(n: &NestedMeta) -> Option<String> {
+    match n {
+        NestedMeta::Meta(meta) => Some(meta.path().to_token_stream().to_string()),
+        NestedMeta::Lit(_) => None,
+    }
+}
+
+/// Reports `n`, which none of the `known` options parsed, on `spanned`. A
+/// known option given the wrong way is shown how it is given, and an unknown
+/// one the known option it is most likely a typo of.
+fn invalid_option(n: &NestedMeta, spanned: impl ToTokens, known: &[(&str, &str)]) -> syn::Error {
+    let names = known.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    let message = match option_name(n) {
+        Some(name) => match known.iter().find(|(known, _)| *known == name) {
+            Some((_, usage)) => format!("expected {}", usage),
+            None => match closest(&name, &names) {
+                Some(suggestion) => {
+                    format!("unknown option `{}`, did you mean `{}`?", name, suggestion)
+                }
+                None => format!(
+                    "unknown option `{}`, expected one of `{}`",
+                    name,
+                    names.join("`, `")
+                ),
+            },
+        },
+        None => format!("expected one of `{}`", names.join("`, `")),
+    };
+    syn::Error::new_spanned(spanned, message)
+}
+
+/// The options given so far in an item's `#[builder(...)]` attributes, to
+/// report those given more than once.
+#[derive(Default)]
+struct SeenOptions(Vec<String>);
+
+impl SeenOptions {
+    /// Records `n`, reporting it if it is one of the `known` options and was
+    /// given before.
+    fn check(&mut self, n: &NestedMeta, known: &[(&str, &str)], errors: &mut Errors) {
+        let name = match option_name(n) {
+            Some(name) if known.iter().any(|(known, _)| *known == name) => name,
+            _ => return,
+        };
+        if REPEATABLE_OPTIONS.contains(&&name[..]) {
+            return;
+        }
+        if self.0.contains(&name) {
+            errors.push(syn::Error::new_spanned(
+                n,
+                format!("`{}` is given more than once, remove one of them", name),
+            ));
+        } else {
+            self.0.push(name);
+        }
+    }
+}
+
+/// Parses the contents of a string literal such as `each = "arg"`. Errors
+/// point at the literal, including those about it ending too early, which
+/// syn would otherwise report at the derive.
+fn parse_lit<T: Parse>(lit: &LitStr) -> Result<T, syn::Error> {
+    lit.parse()
+        .map_err(|e| syn::Error::new_spanned(lit, e.to_string()))
+}
+
+/// Value given to a field that was never set on the builder.
+pub(crate) enum FieldDefault {
+    /// `#[builder(default)]`, using the field type's `Default` impl.
+    Trait,
+    /// `#[builder(default = "expr")]`.
+    Expr(Box<Expr>),
+}
+
+fn default_from_lit(lit: &Lit) -> Result<FieldDefault, syn::Error> {
+    match lit {
+        Lit::Str(lit) => Ok(FieldDefault::Expr(Box::new(parse_lit(lit)?))),
+        // Plain literals such as `default = 30` stand for themselves.
+        lit => Ok(FieldDefault::Expr(Box::new(Expr::Lit(ExprLit {
+            attrs: Vec::new(),
+            lit: lit.clone(),
+        })))),
+    }
+}
+
+/// The attributes listed in `struct_attr(...)` or `field_attr(...)`, copied
+/// as they are onto the builder or one of its fields.
+fn parse_passthrough(list: &MetaList, attrs: &mut Vec<Meta>, errors: &mut Errors) {
+    for n in &list.nested {
+        match n {
+            NestedMeta::Meta(meta) => attrs.push(meta.clone()),
+            NestedMeta::Lit(_) => errors.push(syn::Error::new_spanned(n, "expected an attribute")),
+        }
+    }
+}
+
+/// Options from `setter(...)`, given on a field or on the struct for all of
+/// its fields.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct SetterOptions {
+    /// Setters take `impl Into<T>`.
+    pub into: bool,
+    /// A fallible `try_<field>` setter taking any `TryInto<T>` is generated
+    /// next to the regular one.
+    pub try_into: bool,
+    /// Setters whose `with` function returns a `Result` return its error,
+    /// rather than this being an error.
+    pub fallible: bool,
+}
+
+/// Parses `setter(...)`. `with` is only given for a field, to store the
+/// function its setter calls, and `fallible` is only accepted along with it.
+fn parse_setter_options(
+    nested: &Punctuated<NestedMeta, Token![,]>,
+    options: &mut SetterOptions,
+    mut with: Option<&mut Option<Expr>>,
+    errors: &mut Errors,
+) {
+    let mut seen = SeenOptions::default();
+    for n in nested {
+        seen.check(n, SETTER_OPTIONS, errors);
+        match n {
+            NestedMeta::Meta(Meta::Path(p)) if compare_path_with_str(p, "into") => {
+                options.into = true;
+            }
+            NestedMeta::Meta(Meta::Path(p)) if compare_path_with_str(p, "try_into") => {
+                options.try_into = true;
+            }
+            NestedMeta::Meta(Meta::Path(p)) if compare_path_with_str(p, "fallible") => match with {
+                Some(_) => options.fallible = true,
+                None => errors.push(syn::Error::new_spanned(
+                    n,
+                    "`fallible` only applies to the setter of a field",
+                )),
+            },
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(lit),
+                ..
+            })) if compare_path_with_str(path, "with") => match &mut with {
+                Some(with) => **with = errors.check(parse_lit(lit)),
+                None => errors.push(syn::Error::new_spanned(
+                    n,
+                    "`with` only applies to the setter of a field",
+                )),
+            },
+            _ => errors.push(invalid_option(n, n, SETTER_OPTIONS)),
+        }
+    }
+}
+
+/// Parses a field's `#[builder(...)]` like `parse_meta`, except that `with`
+/// in `setter(...)` can be given any expression, such as a closure, rather
+/// than only a literal. Such an expression is taken out of the list and
+/// returned along with it.
+fn parse_field_meta(attr: &Attribute) -> Result<(Meta, Option<Expr>), syn::Error> {
+    let error = match attr.parse_meta() {
+        Ok(meta) => return Ok((meta, None)),
+        Err(error) => error,
+    };
+    // Anything other than a list fails the same way either way.
+    if attr.tokens.is_empty() {
+        return Err(error);
+    }
+
+    let mut with = None;
+    let nested = attr.parse_args_with(|input: ParseStream| {
+        let mut nested = Punctuated::<NestedMeta, Token![,]>::new();
+        while !input.is_empty() {
+            let is_setter = input.peek(Ident)
+                && input.peek2(token::Paren)
+                && input.fork().parse::<Ident>().is_ok_and(|i| i == "setter");
+            if is_setter {
+                let path = Path::from(input.parse::<Ident>()?);
+                let content;
+                let paren_token = parenthesized!(content in input);
+                let mut setter = Punctuated::new();
+                while !content.is_empty() {
+                    let is_with = content.peek2(Token![=])
+                        && content.fork().parse::<Ident>().is_ok_and(|i| i == "with");
+                    if is_with {
+                        let ident = content.parse::<Ident>()?;
+                        if with.is_some() {
+                            return Err(syn::Error::new(
+                                ident.span(),
+                                "`with` is given more than once, remove one of them",
+                            ));
+                        }
+                        content.parse::<Token![=]>()?;
+                        with = Some(content.parse::<Expr>()?);
+                    } else {
+                        setter.push(content.parse::<NestedMeta>()?);
+                    }
+                    if !content.is_empty() {
+                        content.parse::<Token![,]>()?;
+                    }
+                }
+                nested.push(NestedMeta::Meta(Meta::List(MetaList {
+                    path,
+                    paren_token,
+                    nested: setter,
+                })));
+            } else {
+                nested.push(input.parse::<NestedMeta>()?);
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(nested)
+    })?;
+
+    let meta = Meta::List(MetaList {
+        path: attr.path.clone(),
+        paren_token: Default::default(),
+        nested,
+    });
+    Ok((meta, with))
+}
+
+/// `each = "name"`, or `each(name = "name", collection)` where `collection`
+/// opts in a type the builder does not recognize as a collection.
+pub(crate) struct EachOptions {
+    pub name: Ident,
+    pub collection: bool,
+}
+
+fn parse_each_options(list: &MetaList, errors: &mut Errors) -> Option<EachOptions> {
+    // `None` while no name was given, `Some(None)` for one that failed to parse.
+    let mut name = None;
+    let mut collection = false;
+    for n in &list.nested {
+        match n {
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(lit),
+                ..
+            })) if compare_path_with_str(path, "name") => {
+                name = Some(errors.check(parse_lit(lit)));
+            }
+            NestedMeta::Meta(Meta::Path(path)) if compare_path_with_str(path, "collection") => {
+                collection = true;
+            }
+            _ => errors.push(syn::Error::new_spanned(
+                n,
+                "expected `name = \"...\"` or `collection`",
+            )),
+        }
+    }
+
+    match name {
+        Some(name) => name.map(|name| EachOptions { name, collection }),
+        None => {
+            errors.push(syn::Error::new_spanned(
+                list,
+                "expected `each(name = \"...\")`",
+            ));
+            None
+        }
+    }
+}
+
+/// How `merge` combines an `each` collection set on both builders.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Merge {
+    /// The other builder's items are added to ours.
+    Append,
+    /// The other builder's collection replaces ours, like any other field.
+    Replace,
+}
+
+impl Merge {
+    fn from_lit(lit: &LitStr) -> Result<Self, syn::Error> {
+        match &lit.value()[..] {
+            "append" => Ok(Merge::Append),
+            "replace" => Ok(Merge::Replace),
+            _ => Err(syn::Error::new_spanned(
+                lit,
+                "expected `merge = \"append\"` or `\"replace\"`",
+            )),
+        }
+    }
+}
+
+pub(crate) struct FieldOptions {
+    pub each: Option<EachOptions>,
+    pub name: Option<Ident>,
+    pub default: Option<FieldDefault>,
+    pub setter: SetterOptions,
+    /// The field is an `Option` even though its type does not say so, as
+    /// with an alias such as `type Port = Option<u16>`.
+    pub optional: bool,
+    /// The field's type derives `Builder` too, and the builder holds its
+    /// builder rather than a value.
+    pub sub_builder: bool,
+    /// Only given for fields with an `each` setter.
+    pub merge: Option<Merge>,
+    /// Environment variable read by `fill_from_env`.
+    pub env: Option<LitStr>,
+    /// Attributes for the builder's storage of the field.
+    pub field_attrs: Vec<Meta>,
+    /// The field is left out of the builder and computed by `build()` from
+    /// its `default`.
+    pub skip: bool,
+    /// Function the setter calls with its arguments, storing what it returns.
+    pub with: Option<Expr>,
+}
+
+/// Parses the field's `#[builder(...)]` attributes, reporting every invalid
+/// option rather than only the first.
+pub(crate) fn field_options(attrs: &[Attribute]) -> Result<FieldOptions, syn::Error> {
+    let mut errors = Errors::default();
+    let mut options = FieldOptions {
+        each: None,
+        name: None,
+        default: None,
+        setter: SetterOptions::default(),
+        optional: false,
+        sub_builder: false,
+        merge: None,
+        env: None,
+        field_attrs: Vec::new(),
+        skip: false,
+        with: None,
+    };
+    let mut merge_lit: Option<LitStr> = None;
+    let mut skip_path: Option<Path> = None;
+    let mut seen = SeenOptions::default();
+
+    for a in find_builder_attrs(attrs) {
+        let meta = match parse_field_meta(a) {
+            Ok((meta, with)) => {
+                if with.is_some() {
+                    options.with = with;
+                }
+                meta
+            }
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        let nested = match &meta {
+            Meta::List(MetaList { nested, .. }) => nested,
+            _ => {
+                errors.push(syn::Error::new_spanned(
+                    meta,
+                    "expected `builder(...)` with a list of options",
+                ));
+                continue;
+            }
+        };
+
+        for n in nested {
+            seen.check(n, FIELD_OPTIONS, &mut errors);
+            match n {
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "each") => {
+                    options.each = errors.check(parse_lit(lit)).map(|name| EachOptions {
+                        name,
+                        collection: false,
+                    });
+                }
+                NestedMeta::Meta(Meta::List(list)) if compare_path_with_str(&list.path, "each") => {
+                    options.each = parse_each_options(list, &mut errors);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "name") => {
+                    options.name = errors.check(parse_lit(lit));
+                }
+                NestedMeta::Meta(Meta::Path(path)) if compare_path_with_str(path, "default") => {
+                    options.default = Some(FieldDefault::Trait);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { path, lit, .. }))
+                    if compare_path_with_str(path, "default") =>
+                {
+                    options.default = errors.check(default_from_lit(lit));
+                }
+                NestedMeta::Meta(Meta::List(MetaList { path, nested, .. }))
+                    if compare_path_with_str(path, "setter") =>
+                {
+                    parse_setter_options(
+                        nested,
+                        &mut options.setter,
+                        Some(&mut options.with),
+                        &mut errors,
+                    );
+                }
+                NestedMeta::Meta(Meta::Path(path)) if compare_path_with_str(path, "optional") => {
+                    options.optional = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if compare_path_with_str(path, "skip") => {
+                    options.skip = true;
+                    skip_path = Some(path.clone());
+                }
+                NestedMeta::Meta(Meta::Path(path))
+                    if compare_path_with_str(path, "sub_builder") =>
+                {
+                    options.sub_builder = true;
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "merge") => {
+                    options.merge = errors.check(Merge::from_lit(lit));
+                    merge_lit = Some(lit.clone());
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "env") => {
+                    options.env = Some(lit.clone());
+                }
+                NestedMeta::Meta(Meta::List(list))
+                    if compare_path_with_str(&list.path, "field_attr") =>
+                {
+                    parse_passthrough(list, &mut options.field_attrs, &mut errors);
+                }
+                _ => errors.push(invalid_option(n, &meta, FIELD_OPTIONS)),
+            }
+        }
+    }
+
+    // Other fields are always replaced by a set value.
+    if let (None, Some(lit)) = (&options.each, merge_lit) {
+        errors.push(syn::Error::new_spanned(
+            lit,
+            "`merge` only applies to fields with an `each` setter",
+        ));
+    }
+    // A skipped field has neither storage nor setters for anything else to
+    // apply to.
+    let configured = options.each.is_some()
+        || options.name.is_some()
+        || options.setter.into
+        || options.setter.try_into
+        || options.setter.fallible
+        || options.with.is_some()
+        || options.optional
+        || options.sub_builder
+        || options.merge.is_some()
+        || options.env.is_some()
+        || !options.field_attrs.is_empty();
+    if let (Some(path), true) = (skip_path, configured) {
+        errors.push(syn::Error::new_spanned(
+            path,
+            "`skip` can only be combined with `default`",
+        ));
+    }
+
+    errors.finish()?;
+    Ok(options)
+}
+
+/// How the builder's setters and `build()` take the builder.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Pattern {
+    /// Setters take and return `&mut self`, `build()` clones every field.
+    Mutable,
+    /// Setters and `build()` consume the builder, so no field needs `Clone`.
+    Owned,
+    /// Setters return an updated copy of the builder, leaving it unchanged.
+    Immutable,
+}
+
+impl Pattern {
+    fn from_lit(lit: &LitStr) -> Result<Self, syn::Error> {
+        match &lit.value()[..] {
+            "mutable" => Ok(Pattern::Mutable),
+            "owned" => Ok(Pattern::Owned),
+            "immutable" => Ok(Pattern::Immutable),
+            _ => Err(syn::Error::new_spanned(
+                lit,
+                "expected `pattern = \"owned\"`, `\"mutable\"` or `\"immutable\"`",
+            )),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct StructOptions {
+    pub typestate: bool,
+    pub error: Option<Type>,
+    /// `#[builder(default)]` on the struct: unset fields are taken from the
+    /// struct's own `Default` impl.
+    pub default: bool,
+    pub setter: SetterOptions,
+    /// `None` unless given with `#[builder(pattern = "...")]`.
+    pub pattern: Option<Pattern>,
+    /// Function checking the built value before `build()` returns it.
+    pub validate: Option<Path>,
+    /// Every field without `env = "..."` is read by `fill_from_env` from the
+    /// variable named by this prefix and the field's name in upper case.
+    pub env_prefix: Option<String>,
+    /// Name of the builder, `<Struct>Builder` unless given.
+    pub name: Option<Ident>,
+    /// Visibility of the builder, its methods, its error type and the method
+    /// returning it. `pub` unless given.
+    pub vis: Option<Visibility>,
+    /// Prepended to the name of every field's setter.
+    pub setter_prefix: Option<String>,
+    /// Name of the method returning a new builder, `builder` unless given.
+    pub constructor: Option<Ident>,
+    /// Name of the builder's `build` method.
+    pub build_fn: Option<Ident>,
+    /// Traits derived for the builder.
+    pub derives: Vec<Path>,
+    /// Attributes for the builder.
+    pub struct_attrs: Vec<Meta>,
+    /// `#[builder(no_std)]`: generated code only uses `core` and `alloc`.
+    pub no_std: Option<Path>,
+    /// `#[builder(deserialize)]`: the builder derives `serde::Deserialize`.
+    pub deserialize: Option<Path>,
+}
+
+/// Parses the struct's `#[builder(...)]` attributes, reporting every invalid
+/// option rather than only the first.
+pub(crate) fn struct_options(attrs: &[Attribute]) -> Result<StructOptions, syn::Error> {
+    let mut errors = Errors::default();
+    let mut options = StructOptions::default();
+    let mut pattern_lit = None;
+    let mut seen = SeenOptions::default();
+
+    for a in find_builder_attrs(attrs) {
+        let meta = match a.parse_meta() {
+            Ok(meta) => meta,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        let nested = match &meta {
+            Meta::List(MetaList { nested, .. }) => nested,
+            _ => {
+                errors.push(syn::Error::new_spanned(
+                    meta,
+                    "expected `builder(...)` with a list of options",
+                ));
+                continue;
+            }
+        };
+
+        for n in nested {
+            seen.check(n, STRUCT_OPTIONS, &mut errors);
+            match n {
+                NestedMeta::Meta(Meta::Path(p)) if compare_path_with_str(p, "typestate") => {
+                    options.typestate = true;
+                }
+                NestedMeta::Meta(Meta::Path(p)) if compare_path_with_str(p, "default") => {
+                    options.default = true;
+                }
+                NestedMeta::Meta(Meta::Path(p)) if compare_path_with_str(p, "no_std") => {
+                    options.no_std = Some(p.clone());
+                }
+                NestedMeta::Meta(Meta::Path(p)) if compare_path_with_str(p, "deserialize") => {
+                    options.deserialize = Some(p.clone());
+                }
+                NestedMeta::Meta(Meta::List(MetaList { path, nested, .. }))
+                    if compare_path_with_str(path, "setter") =>
+                {
+                    parse_setter_options(nested, &mut options.setter, None, &mut errors);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "error") => {
+                    options.error = errors.check(parse_lit(lit));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "pattern") => {
+                    options.pattern = errors.check(Pattern::from_lit(lit));
+                    pattern_lit = Some(lit.clone());
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "validate") => {
+                    options.validate = errors.check(parse_lit(lit));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "env_prefix") => {
+                    options.env_prefix = Some(lit.value());
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "name") => {
+                    options.name = errors.check(parse_lit(lit));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "vis") => {
+                    options.vis = errors.check(parse_lit(lit));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "setter_prefix") => {
+                    // Checked here so that prefixed names are always valid.
+                    match syn::parse_str::<Ident>(&format!("{}field", lit.value())) {
+                        Ok(_) => options.setter_prefix = Some(lit.value()),
+                        Err(_) => errors.push(syn::Error::new_spanned(
+                            lit,
+                            "expected a prefix such as `setter_prefix = \"with_\"`",
+                        )),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "constructor") => {
+                    options.constructor = errors.check(parse_lit(lit));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if compare_path_with_str(path, "build_fn") => {
+                    options.build_fn = errors.check(parse_lit(lit));
+                }
+                NestedMeta::Meta(Meta::List(MetaList { path, nested, .. }))
+                    if compare_path_with_str(path, "derive") =>
+                {
+                    for n in nested {
+                        match n {
+                            NestedMeta::Meta(Meta::Path(path)) => {
+                                options.derives.push(path.clone())
+                            }
+                            _ => errors
+                                .push(syn::Error::new_spanned(n, "expected a trait to derive")),
+                        }
+                    }
+                }
+                NestedMeta::Meta(Meta::List(list))
+                    if compare_path_with_str(&list.path, "struct_attr") =>
+                {
+                    parse_passthrough(list, &mut options.struct_attrs, &mut errors);
+                }
+                _ => errors.push(invalid_option(n, n, STRUCT_OPTIONS)),
+            }
+        }
+    }
+
+    // A typestate builder changes type with every required field it is given,
+    // which only works by consuming it.
+    match (options.typestate, options.pattern, pattern_lit) {
+        (true, Some(pattern), Some(lit)) if pattern != Pattern::Owned => {
+            errors.push(syn::Error::new_spanned(
+                lit,
+                "typestate builders always use the owned pattern",
+            ));
+        }
+        _ => {}
+    }
+    // Environment variables are read through `std::env`.
+    if let (Some(no_std), Some(_)) = (&options.no_std, &options.env_prefix) {
+        errors.push(syn::Error::new_spanned(
+            no_std,
+            "`env_prefix` reads environment variables, which needs `std`",
+        ));
+    }
+
+    // Only crates asking for the feature depend on serde, and which fields
+    // of a typestate builder are set is part of its type.
+    if let Some(deserialize) = &options.deserialize {
+        if !cfg!(feature = "serde") {
+            errors.push(syn::Error::new_spanned(
+                deserialize,
+                "`deserialize` needs the `serde` feature of `derive_builder`",
+            ));
+        } else if options.typestate {
+            errors.push(syn::Error::new_spanned(
+                deserialize,
+                "typestate builders cannot be deserialized",
+            ));
+        }
+    }
+
+    errors.finish()?;
+    Ok(options)
+}
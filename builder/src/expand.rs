@@ -0,0 +1,1456 @@
+use crate::attrs::{FieldDefault, Merge, Pattern};
+use crate::errors::Errors;
+use crate::field::{BuilderField, CollectionItem, FieldWrapperType};
+use crate::function::FnCall;
+use crate::generics::{generic_args, impl_params, phantom_params};
+use crate::naming::unraw;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{Generics, Ident, Meta, Path, Type, Visibility};
+
+/// Everything needed to generate the builder for one struct or enum variant.
+pub(crate) struct BuilderContext<'a> {
+    /// The type the builder builds, which gets the entry point method.
+    pub st_ident: &'a Ident,
+    /// Path used to construct the built value, `Foo` for a struct and
+    /// `Foo::Variant` for an enum variant.
+    pub ctor: TokenStream,
+    /// Name of the method returning a new builder, which function builders
+    /// generate along with the function instead.
+    pub entry_fn: Option<Ident>,
+    pub builder_ident: Ident,
+    /// What the builder's docs say it builds, linking to it.
+    pub doc_target: String,
+    /// Visibility of the builder, its methods, its error type and `entry_fn`.
+    pub vis: Visibility,
+    /// Name of the method building the value.
+    pub build_fn: Ident,
+    pub error_ident: Ident,
+    /// The error type returned by `build()`, either `error_ident` or the one
+    /// named by `#[builder(error = "...")]`.
+    pub error_ty: TokenStream,
+    pub generics: &'a Generics,
+    pub fields: Vec<BuilderField<'a>>,
+    /// Fields marked `#[builder(skip)]`, which only `build()` deals with.
+    pub skipped: Vec<BuilderField<'a>>,
+    /// Unset fields fall back to the built type's `Default` impl.
+    pub struct_default: bool,
+    pub pattern: Pattern,
+    /// Called as `validate(&built)` and returning `Result<(), E>` with
+    /// `E: Display`. Its error is reported as `<Builder>Error::Validation`.
+    pub validate: Option<&'a Path>,
+    /// Traits derived for the builder.
+    pub derives: &'a [Path],
+    /// Attributes for the builder, given with `struct_attr(...)`.
+    pub struct_attrs: &'a [Meta],
+    /// Whether to generate `From<Foo>` for the builder and `Foo::to_builder`,
+    /// which only makes sense for structs as an enum value may be any variant.
+    pub round_trip: bool,
+    /// Whether generated code may only use `core` and `alloc`, as asked for
+    /// with `#[builder(no_std)]`.
+    pub no_std: bool,
+    /// Whether the builder derives `serde::Deserialize`, as asked for with
+    /// `#[builder(deserialize)]`.
+    pub deserialize: bool,
+    /// For the builder of a function's arguments, how `build_fn` calls the
+    /// function with them rather than returning them.
+    pub call: Option<FnCall>,
+}
+
+impl<'a> BuilderContext<'a> {
+    /// Path generated code reaches `core` items through.
+    pub fn core(&self) -> TokenStream {
+        match self.no_std {
+            true => quote! { ::core },
+            false => quote! { std },
+        }
+    }
+
+    /// Path generated code reaches `alloc` items, such as `Vec` and `String`,
+    /// through.
+    pub fn alloc(&self) -> TokenStream {
+        match self.no_std {
+            true => quote! { ::alloc },
+            false => quote! { std },
+        }
+    }
+
+    /// The type the builder stores a field as.
+    pub fn storage_ty(&self, f: &BuilderField) -> TokenStream {
+        let core = self.core();
+        let fty = f.ty;
+        match (&f.sub_builder, &f.wrapper_ty) {
+            (Some(sub_builder), _) => quote! { #core::option::Option<#sub_builder> },
+            (None, FieldWrapperType::Option(_)) => quote! { #fty },
+            (None, _) => quote! { #core::option::Option<#fty> },
+        }
+    }
+
+    /// The builder's storage for each field.
+    pub fn storage(&self) -> Vec<TokenStream> {
+        self.fields
+            .iter()
+            .map(|f| {
+                let fi = &f.ident;
+                let ty = self.storage_ty(f);
+                let attrs = &f.attrs;
+                let cfgs = f.cfg_attrs();
+                quote! {
+                    #cfgs
+                    #(#[#attrs])*
+                    #fi: #ty
+                }
+            })
+            .collect()
+    }
+
+    /// Initial values for the builder's storage.
+    pub fn storage_defaults(&self) -> Vec<TokenStream> {
+        let core = self.core();
+        self.fields
+            .iter()
+            .map(|f| {
+                let fi = &f.ident;
+                let cfgs = f.cfg_attrs();
+                quote! { #cfgs #fi: #core::option::Option::None }
+            })
+            .collect()
+    }
+
+    /// The value `build()` uses for a field that was never set, or `None` if
+    /// there is nothing to fall back to.
+    ///
+    /// A default on the field itself wins over the struct-level default,
+    /// which in turn wins over the empty collection an `each` field starts
+    /// out as.
+    pub fn fallback(&self, f: &BuilderField) -> Option<TokenStream> {
+        let core = self.core();
+        match &f.default {
+            Some(FieldDefault::Trait) => Some(quote! { #core::default::Default::default() }),
+            Some(FieldDefault::Expr(expr)) => Some(quote! { #expr }),
+            None if self.struct_default => {
+                let member = &f.member;
+                Some(quote! { __default.#member })
+            }
+            None if f.each.is_some() => Some(quote! { #core::default::Default::default() }),
+            None => None,
+        }
+    }
+
+    /// Whether `build()` fails when this field was never set. Fields with a
+    /// sub-builder never are, as it is built even when left untouched.
+    pub fn is_required(&self, f: &BuilderField) -> bool {
+        !matches!(f.wrapper_ty, FieldWrapperType::Option(_))
+            && f.sub_builder.is_none()
+            && self.fallback(f).is_none()
+    }
+
+    /// The built type, with its generic arguments.
+    pub fn st_ty(&self) -> TokenStream {
+        let st_ident = self.st_ident;
+        let st_args = generic_args(self.generics);
+        quote! { #st_ident<#(#st_args),*> }
+    }
+
+    /// Allows generated code reading or building the fields to use deprecated
+    /// ones, as only the users of their setters are to be warned.
+    pub fn allow_deprecated(&self) -> TokenStream {
+        let any = self
+            .fields
+            .iter()
+            .chain(&self.skipped)
+            .any(|f| f.deprecated.is_some());
+        match any {
+            true => quote! { #[allow(deprecated)] },
+            false => quote! {},
+        }
+    }
+
+    /// Bounds that `build()` needs on top of the builder's own.
+    pub fn build_bounds(&self) -> Vec<TokenStream> {
+        let core = self.core();
+        let mut bounds = Vec::new();
+        if self.struct_default {
+            let st_ty = self.st_ty();
+            bounds.push(quote! { #st_ty: #core::default::Default });
+        }
+        bounds
+    }
+
+    /// Generates `impl From<Foo> for FooBuilder` and `Foo::to_builder()`,
+    /// returning `builder_ty` with every field of the value already set.
+    pub fn round_trip(&self, builder_ty: &TokenStream) -> TokenStream {
+        let core = self.core();
+        if !self.round_trip {
+            return quote! {};
+        }
+
+        let builder_ident = &self.builder_ident;
+        let vis = &self.vis;
+        let st_ty = self.st_ty();
+        let st_impl_params = impl_params(self.generics);
+        let where_clause = &self.generics.where_clause;
+        let allow_deprecated = self.allow_deprecated();
+        let field_idents = self.fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+        let cfgs = self
+            .fields
+            .iter()
+            .map(|f| f.cfg_attrs())
+            .collect::<Vec<_>>();
+        // Option fields are stored as they are, sub-builder fields as their
+        // builder and everything else as `Some`.
+        let stored = |f: &BuilderField, val: TokenStream| match (&f.sub_builder, &f.wrapper_ty) {
+            (Some(sub_builder), _) => {
+                quote! { #core::option::Option::Some(<#sub_builder>::__sub_from(#val)) }
+            }
+            (None, FieldWrapperType::Option(_)) => val,
+            (None, _) => quote! { #core::option::Option::Some(#val) },
+        };
+        let moved = self
+            .fields
+            .iter()
+            .map(|f| {
+                let member = &f.member;
+                stored(f, quote! { value.#member })
+            })
+            .collect::<Vec<_>>();
+        let cloned = self
+            .fields
+            .iter()
+            .map(|f| {
+                let member = &f.member;
+                stored(f, quote! { #core::clone::Clone::clone(&self.#member) })
+            })
+            .collect::<Vec<_>>();
+        // The bounds are higher-ranked so that they are only checked where
+        // `to_builder` is called, rather than rejecting structs with fields
+        // that are not `Clone` outright.
+        let clone_bounds = self.fields.iter().map(|f| {
+            let fty = f.ty;
+            quote! { for<'__c> #fty: #core::clone::Clone }
+        });
+
+        quote! {
+            impl<#(#st_impl_params),*> #core::convert::From<#st_ty> for #builder_ty #where_clause {
+                #allow_deprecated
+                fn from(value: #st_ty) -> Self {
+                    #builder_ident {
+                        #(#cfgs #field_idents: #moved,)*
+                        __phantom: #core::marker::PhantomData,
+                    }
+                }
+            }
+
+            impl<#(#st_impl_params),*> #st_ty #where_clause {
+                /// Returns a builder with every field set to its value here.
+                #allow_deprecated
+                #vis fn to_builder(&self) -> #builder_ty
+                where
+                    #(#clone_bounds,)*
+                {
+                    #builder_ident {
+                        #(#cfgs #field_idents: #cloned,)*
+                        __phantom: #core::marker::PhantomData,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Docs of the builder struct, then the derives and attributes the user
+    /// asked for on it.
+    pub fn builder_attrs(&self) -> TokenStream {
+        let doc = format!("Builder for {}.", self.doc_target);
+        let derives = self.derives;
+        let struct_attrs = self.struct_attrs;
+        let derive = (!derives.is_empty()).then(|| quote! { #[derive(#(#derives),*)] });
+        quote! {
+            #[doc = #doc]
+            #derive
+            #(#[#struct_attrs])*
+        }
+    }
+
+    /// Generates `<Builder>Error`, the error returned by `build()` unless the
+    /// struct names its own error type with `#[builder(error = "...")]`.
+    pub fn error_type(&self) -> TokenStream {
+        let core = self.core();
+        let alloc = self.alloc();
+        let error_ident = &self.error_ident;
+        let vis = &self.vis;
+        let doc = format!(
+            "Error returned by [`{}::{}`].",
+            self.builder_ident, self.build_fn
+        );
+        quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            #[non_exhaustive]
+            #vis enum #error_ident {
+                /// Required fields that were never set, in declaration order.
+                /// Fields of sub-builders are given by their path, such as
+                /// `server.port`.
+                MissingFields(#alloc::vec::Vec<#alloc::string::String>),
+                /// The built value was rejected by the `validate` function.
+                Validation(#alloc::string::String),
+                /// An environment variable read by `fill_from_env` could not
+                /// be parsed.
+                InvalidEnv {
+                    /// Name of the variable.
+                    var: #alloc::string::String,
+                    /// Why its value could not be parsed.
+                    message: #alloc::string::String,
+                },
+            }
+
+            impl #core::fmt::Display for #error_ident {
+                fn fmt(&self, f: &mut #core::fmt::Formatter<'_>) -> #core::fmt::Result {
+                    match self {
+                        #error_ident::MissingFields(fields) if fields.len() == 1 => {
+                            write!(f, "missing field: {}", fields[0])
+                        }
+                        #error_ident::MissingFields(fields) => {
+                            write!(f, "missing fields: {}", fields.join(", "))
+                        }
+                        #error_ident::Validation(message) => f.write_str(message),
+                        #error_ident::InvalidEnv { var, message } => {
+                            write!(f, "invalid value in environment variable {}: {}", var, message)
+                        }
+                    }
+                }
+            }
+
+            impl #core::error::Error for #error_ident {}
+
+            impl #error_ident {
+                /// Adds this error, raised by the sub-builder of `field`, to
+                /// those of the builder containing it.
+                #[doc(hidden)]
+                pub fn __nest(
+                    self,
+                    field: &str,
+                    missing: &mut #alloc::vec::Vec<#alloc::string::String>,
+                    invalid: &mut #core::option::Option<#alloc::string::String>,
+                ) {
+                    match self {
+                        #error_ident::MissingFields(fields) => {
+                            for name in fields {
+                                missing.push(#alloc::format!("{}.{}", field, name));
+                            }
+                        }
+                        error => {
+                            invalid.get_or_insert_with(|| #alloc::format!("{}: {}", field, error));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generates the body of `build()`. Every field is first read out of the
+    /// builder with `take`, which either clones or moves it, and all unset
+    /// required fields are then reported together rather than stopping at the
+    /// first one, including those of sub-builders. Skipped fields are then
+    /// computed from the others.
+    ///
+    /// Errors are always `<Builder>Error`, even when `build()` converts them
+    /// to a user-defined type, so that a containing builder can read them.
+    pub fn build_body(&self, take: impl Fn(&Ident) -> TokenStream) -> TokenStream {
+        let core = self.core();
+        let alloc = self.alloc();
+        let ctor = &self.ctor;
+        let error_ident = &self.error_ident;
+        let field_idents = self.fields.iter().map(|f| f.local()).collect::<Vec<_>>();
+        let cfgs = self
+            .fields
+            .iter()
+            .map(|f| f.cfg_attrs())
+            .collect::<Vec<_>>();
+        let members = self.fields.iter().map(|f| &f.member);
+        let takes = self.fields.iter().map(|f| {
+            // Sub-builders are built in place, leaving a `Result`. Errors
+            // about the sub-builder's pattern point at the field's type.
+            if let Some(sub_builder) = &f.sub_builder {
+                let fi = &f.ident;
+                let span = f.ty.span();
+                let unset = match self.fallback(f) {
+                    Some(fallback) => quote! { #core::result::Result::Ok(#fallback) },
+                    None => quote_spanned! {span=>
+                        <#sub_builder>::__sub_build(
+                            &<#sub_builder as #core::default::Default>::default(),
+                        )
+                    },
+                };
+                let built = quote_spanned! {span=> <#sub_builder>::__sub_build(builder) };
+                return quote! {
+                    match &self.#fi {
+                        #core::option::Option::Some(builder) => #built,
+                        #core::option::Option::None => #unset,
+                    }
+                };
+            }
+
+            let taken = take(&f.ident);
+            match (self.fallback(f), &f.wrapper_ty) {
+                (Some(fallback), FieldWrapperType::Option(_)) => quote! {
+                    match #taken {
+                        #core::option::Option::Some(val) => #core::option::Option::Some(val),
+                        #core::option::Option::None => #fallback,
+                    }
+                },
+                (Some(fallback), _) => quote! {
+                    match #taken {
+                        #core::option::Option::Some(val) => val,
+                        #core::option::Option::None => #fallback,
+                    }
+                },
+                (None, _) => taken,
+            }
+        });
+        // Fields that may be missing, in declaration order: unset required
+        // fields as `None` and failed sub-builders as `Err`.
+        let checked = self
+            .fields
+            .iter()
+            .filter(|f| self.is_required(f) || f.sub_builder.is_some())
+            .collect::<Vec<_>>();
+        let struct_default = if self.struct_default {
+            let st_ty = self.st_ty();
+            quote! { let __default: #st_ty = #core::default::Default::default(); }
+        } else {
+            quote! {}
+        };
+
+        let validate = self.validate.map(|validate| {
+            quote! {
+                if let #core::result::Result::Err(e) = #validate(&__built) {
+                    return #core::result::Result::Err(
+                        #error_ident::Validation(#alloc::string::ToString::to_string(&e)),
+                    );
+                }
+            }
+        });
+        // Skipped fields come last, so that their defaults can use any other
+        // field by name, and in declaration order among themselves.
+        let skipped_idents = self.skipped.iter().map(|f| f.local());
+        let skipped_members = self.skipped.iter().map(|f| &f.member);
+        let skipped_cfgs = self
+            .skipped
+            .iter()
+            .map(|f| f.cfg_attrs())
+            .collect::<Vec<_>>();
+        let computed = self.skipped.iter().map(|f| {
+            let fi = f.local();
+            let cfgs = f.cfg_attrs();
+            let value = self
+                .fallback(f)
+                .unwrap_or_else(|| quote! { #core::default::Default::default() });
+            quote! { #cfgs let #fi = #value; }
+        });
+        let construct = quote! {
+            {
+                #(#computed)*
+                let __built = #ctor {
+                    #(#cfgs #members: #field_idents,)*
+                    #(#skipped_cfgs #skipped_members: #skipped_idents,)*
+                };
+                #validate
+                #core::result::Result::Ok(__built)
+            }
+        };
+
+        if checked.is_empty() {
+            return quote! {
+                #struct_default
+                #(#cfgs let #field_idents = #takes;)*
+                #construct
+            };
+        }
+
+        let checked_idents = checked.iter().map(|f| f.local()).collect::<Vec<_>>();
+        let patterns = checked.iter().map(|f| {
+            let fi = f.local();
+            match f.sub_builder {
+                Some(_) => quote! { #core::result::Result::Ok(#fi) },
+                None => quote! { #core::option::Option::Some(#fi) },
+            }
+        });
+        let report = checked.iter().map(|f| {
+            let fi = f.local();
+            let name = unraw(&f.ident);
+            match f.sub_builder {
+                Some(_) => quote! {
+                    if let #core::result::Result::Err(e) = #fi {
+                        e.__nest(#name, &mut __missing, &mut __invalid);
+                    }
+                },
+                None => quote! {
+                    if #fi.is_none() {
+                        __missing.push(#alloc::string::String::from(#name));
+                    }
+                },
+            }
+        });
+        // A sub-builder that failed validation only matters when nothing is
+        // missing, as with the builder's own `validate`.
+        let (invalid, error) = match checked.iter().any(|f| f.sub_builder.is_some()) {
+            true => (
+                quote! {
+                    let mut __invalid: #core::option::Option<#alloc::string::String> =
+                        #core::option::Option::None;
+                },
+                quote! {
+                match __invalid {
+                    #core::option::Option::Some(message) if __missing.is_empty() => {
+                        #error_ident::Validation(message)
+                    }
+                    _ => #error_ident::MissingFields(__missing),
+                }
+                },
+            ),
+            false => (quote! {}, quote! { #error_ident::MissingFields(__missing) }),
+        };
+
+        quote! {
+            #struct_default
+            #(#cfgs let #field_idents = #takes;)*
+            match (#(#checked_idents,)*) {
+                (#(#patterns,)*) => #construct,
+                (#(#checked_idents,)*) => {
+                    let mut __missing = #alloc::vec::Vec::new();
+                    #invalid
+                    #(#report)*
+                    #core::result::Result::Err(#error)
+                }
+            }
+        }
+    }
+
+    /// Generates a setter called `name`, taking `params`, whose body runs
+    /// `update` against the builder bound as `__builder`. How the builder is
+    /// received and returned depends on the builder pattern.
+    pub fn setter(
+        &self,
+        name: &Ident,
+        generics: TokenStream,
+        params: TokenStream,
+        bounds: TokenStream,
+        update: TokenStream,
+    ) -> TokenStream {
+        self.method(name, generics, params, bounds, update, None)
+    }
+
+    /// Like `setter`, but returning the builder in a `Result` with `error` as
+    /// its error type, so that `update` can fail with `?`.
+    pub fn fallible_setter(
+        &self,
+        name: &Ident,
+        params: TokenStream,
+        update: TokenStream,
+        error: &TokenStream,
+    ) -> TokenStream {
+        self.method(name, quote! {}, params, quote! {}, update, Some(error))
+    }
+
+    fn method(
+        &self,
+        name: &Ident,
+        generics: TokenStream,
+        params: TokenStream,
+        bounds: TokenStream,
+        update: TokenStream,
+        error: Option<&TokenStream>,
+    ) -> TokenStream {
+        let core = self.core();
+        let vis = &self.vis;
+        let field_idents = self.fields.iter().map(|f| &f.ident);
+        let cfgs = self.fields.iter().map(|f| f.cfg_attrs());
+        let field_tys = self.fields.iter().map(|f| f.ty);
+        let (receiver, ret, init, clone_bounds) = match self.pattern {
+            Pattern::Mutable => (
+                quote! { &mut self },
+                quote! { &mut Self },
+                quote! { let __builder = self; },
+                quote! {},
+            ),
+            Pattern::Owned => (
+                quote! { self },
+                quote! { Self },
+                quote! { let mut __builder = self; },
+                quote! {},
+            ),
+            Pattern::Immutable => (
+                quote! { &self },
+                quote! { Self },
+                quote! {
+                    let mut __builder = Self {
+                        #(#cfgs #field_idents: #core::clone::Clone::clone(&self.#field_idents),)*
+                        __phantom: #core::marker::PhantomData,
+                    };
+                },
+                quote! { #(#field_tys: #core::clone::Clone,)* },
+            ),
+        };
+        let (ret, result) = match error {
+            Some(error) => (
+                quote! { #core::result::Result<#ret, #error> },
+                quote! { #core::result::Result::Ok(__builder) },
+            ),
+            None => (ret, quote! { __builder }),
+        };
+        quote! {
+            #vis fn #name #generics(#receiver, #params) -> #ret
+            where
+                #clone_bounds
+                #bounds
+            {
+                #init
+                #update
+                #result
+            }
+        }
+    }
+
+    /// Parameter type of a setter of `f` storing a `ty`, and the expression
+    /// turning the parameter `arg` into one.
+    pub fn setter_input(
+        &self,
+        f: &BuilderField,
+        ty: &Type,
+        arg: &Ident,
+    ) -> (TokenStream, TokenStream) {
+        let core = self.core();
+        if f.setter.into {
+            (
+                quote! { impl #core::convert::Into<#ty> },
+                quote! { #core::convert::Into::into(#arg) },
+            )
+        } else {
+            (quote! { #ty }, quote! { #arg })
+        }
+    }
+
+    /// Parameters of the setter of the whole field `f`, the value it stores
+    /// and, if setting it can fail, its error type.
+    pub fn field_setter_input(
+        &self,
+        f: &BuilderField,
+    ) -> (TokenStream, TokenStream, Option<TokenStream>) {
+        match &f.with {
+            Some(with) => {
+                let params = with.params.iter().map(|(ident, ty)| quote! { #ident: #ty });
+                let error = with.error.as_ref().map(|error| quote! { #error });
+                (quote! { #(#params),* }, with.value.clone(), error)
+            }
+            None => {
+                let (arg_ty, value) = self.setter_input(f, f.setter_ty(), &format_ident!("val"));
+                (quote! { val: #arg_ty }, value, None)
+            }
+        }
+    }
+
+    /// Generates the setter of the whole field `f` and its `try_` variant,
+    /// for builders whose type does not change as fields are set.
+    pub fn field_setter(&self, f: &BuilderField) -> TokenStream {
+        let core = self.core();
+        let fi = &f.ident;
+        let setter_fi = &f.setter_ident;
+        let (params, value, error) = self.field_setter_input(f);
+        let update = quote! { __builder.#fi = #core::option::Option::Some(#value); };
+        let setter = match &error {
+            Some(error) => self.fallible_setter(setter_fi, params, update, error),
+            None => self.setter(setter_fi, quote! {}, params, quote! {}, update),
+        };
+        let attrs = f.setter_attrs(f.setter_docs(&format!("Sets `{}`.", unraw(fi))));
+        let try_setter = f
+            .setter
+            .try_into
+            .then(|| self.try_setter(f, setter_fi, f.setter_ty()));
+        quote! {
+            #attrs
+            #setter
+            #try_setter
+        }
+    }
+
+    /// Generates the `each` setter of a collection field, adding one item at a
+    /// time, and `extend_<field>` adding any number of them at once.
+    pub fn each_methods(&self, f: &BuilderField) -> Option<TokenStream> {
+        let core = self.core();
+        let each = f.each.as_ref()?;
+        let each_name = &each.name;
+        let fi = &f.ident;
+        let fty = f.ty;
+        let extend_fi = format_ident!("extend_{}", unraw(fi));
+        let collection = quote! {
+            __builder.#fi.get_or_insert_with(#core::default::Default::default)
+        };
+
+        let (each_setter, item_ty) = match each.item {
+            CollectionItem::Value(ty) => {
+                let (arg_ty, value) = self.setter_input(f, ty, &format_ident!("val"));
+                let setter = self.setter(
+                    each_name,
+                    quote! {},
+                    quote! { val: #arg_ty },
+                    quote! {},
+                    quote! {
+                        #core::iter::Extend::extend(#collection, #core::iter::once(#value));
+                    },
+                );
+                let try_setter = f.setter.try_into.then(|| self.try_setter(f, each_name, ty));
+                (quote! { #setter #try_setter }, Some(quote! { #ty }))
+            }
+            CollectionItem::Entry(key_ty, value_ty) => {
+                let (key_arg_ty, key) = self.setter_input(f, key_ty, &format_ident!("key"));
+                let (value_arg_ty, value) = self.setter_input(f, value_ty, &format_ident!("value"));
+                let setter = self.setter(
+                    each_name,
+                    quote! {},
+                    quote! { key: #key_arg_ty, value: #value_arg_ty },
+                    quote! {},
+                    quote! {
+                        #core::iter::Extend::extend(#collection, #core::iter::once((#key, #value)));
+                    },
+                );
+                (setter, Some(quote! { (#key_ty, #value_ty) }))
+            }
+            CollectionItem::Any => {
+                let setter = self.setter(
+                    each_name,
+                    quote! { <__I> },
+                    quote! { item: __I },
+                    quote! { #fty: #core::iter::Extend<__I>, },
+                    quote! {
+                        #core::iter::Extend::extend(#collection, #core::iter::once(item));
+                    },
+                );
+                (setter, None)
+            }
+        };
+
+        let extend = match item_ty {
+            Some(item_ty) => self.setter(
+                &extend_fi,
+                quote! { <__I: #core::iter::IntoIterator<Item = #item_ty>> },
+                quote! { items: __I },
+                quote! {},
+                quote! { #core::iter::Extend::extend(#collection, items); },
+            ),
+            None => self.setter(
+                &extend_fi,
+                quote! { <__I: #core::iter::IntoIterator> },
+                quote! { items: __I },
+                quote! { #fty: #core::iter::Extend<<__I as #core::iter::IntoIterator>::Item>, },
+                quote! { #core::iter::Extend::extend(#collection, items); },
+            ),
+        };
+
+        let each_attrs =
+            f.setter_attrs(f.method_docs(&format!("Adds one element to `{}`.", unraw(fi))));
+        let extend_attrs = f.setter_attrs(f.method_docs(&format!(
+            "Adds every element of `items` to `{}`.",
+            unraw(fi)
+        )));
+        Some(quote! {
+            #each_attrs
+            #each_setter
+            #extend_attrs
+            #extend
+        })
+    }
+
+    /// Generates `merge`, which overrides the builder's fields with those set
+    /// on another builder, and the hidden `__merge` doing so in place, through
+    /// which sub-builders are merged as well.
+    pub fn merge_fns(&self) -> TokenStream {
+        let core = self.core();
+        let mut bounds = Vec::new();
+        let merges = self
+            .fields
+            .iter()
+            .map(|f| {
+                let fi = &f.ident;
+                let fty = f.ty;
+                let combine = match (&f.sub_builder, &f.each) {
+                    (Some(_), _) => quote! { ours.__merge(theirs) },
+                    (None, Some(each)) if each.merge == Merge::Append => {
+                        bounds.push(quote! {
+                            #fty: #core::iter::IntoIterator
+                                + #core::iter::Extend<<#fty as #core::iter::IntoIterator>::Item>
+                        });
+                        quote! { #core::iter::Extend::extend(ours, theirs) }
+                    }
+                    _ => quote! { *ours = theirs },
+                };
+                let cfgs = f.cfg_attrs();
+                quote! {
+                    #cfgs
+                    match (&mut self.#fi, other.#fi) {
+                        (#core::option::Option::Some(ours), #core::option::Option::Some(theirs)) => {
+                            #combine;
+                        }
+                        (ours, theirs @ #core::option::Option::Some(_)) => *ours = theirs,
+                        _ => {}
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        let merge = self.setter(
+            &format_ident!("merge"),
+            quote! {},
+            quote! { other: Self },
+            quote! { #(#bounds,)* },
+            quote! { __builder.__merge(other); },
+        );
+
+        quote! {
+            /// Overrides the fields of this builder with those set on `other`.
+            #merge
+
+            #[doc(hidden)]
+            pub fn __merge(&mut self, other: Self)
+            where
+                #(#bounds,)*
+            {
+                #(#merges)*
+            }
+        }
+    }
+
+    /// Generates `fill_from_env`, parsing every field that reads an
+    /// environment variable from it when it is present, and an impl with
+    /// `from_env` returning a new builder filled that way. Nothing is
+    /// generated when no field reads a variable.
+    pub fn env_fns(&self) -> (TokenStream, TokenStream) {
+        let core = self.core();
+        let alloc = self.alloc();
+        let error_ident = &self.error_ident;
+        let error_ty = &self.error_ty;
+        let reads = self
+            .fields
+            .iter()
+            .filter_map(|f| {
+                let var = f.env.as_ref()?;
+                let fi = &f.ident;
+                let ty = f.setter_ty();
+                let invalid = quote! {
+                    return #core::result::Result::Err(#core::convert::From::from(
+                        #error_ident::InvalidEnv {
+                            var: #alloc::string::String::from(#var),
+                            message: #alloc::string::ToString::to_string(&e),
+                        },
+                    ))
+                };
+                let cfgs = f.cfg_attrs();
+                Some(quote! {
+                    #cfgs
+                    match std::env::var(#var) {
+                        #core::result::Result::Ok(value) => {
+                            match <#ty as #core::str::FromStr>::from_str(&value) {
+                                #core::result::Result::Ok(val) => {
+                                    __builder.#fi = #core::option::Option::Some(val);
+                                }
+                                #core::result::Result::Err(e) => #invalid,
+                            }
+                        }
+                        #core::result::Result::Err(std::env::VarError::NotPresent) => {}
+                        #core::result::Result::Err(e) => #invalid,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        if reads.is_empty() {
+            return (quote! {}, quote! {});
+        }
+
+        let fill_from_env = self.fallible_setter(
+            &format_ident!("fill_from_env"),
+            quote! {},
+            quote! { #(#reads)* },
+            error_ty,
+        );
+        let fill_from_env = quote! {
+            /// Sets the fields read from environment variables to those that
+            /// are present, failing if one of them cannot be parsed.
+            #fill_from_env
+        };
+
+        let builder_ident = &self.builder_ident;
+        let vis = &self.vis;
+        let st_impl_params = impl_params(self.generics);
+        let st_args = generic_args(self.generics);
+        let where_clause = &self.generics.where_clause;
+        let from_env = match self.pattern {
+            Pattern::Mutable => quote! {
+                let mut builder: Self = #core::default::Default::default();
+                builder.fill_from_env()?;
+                #core::result::Result::Ok(builder)
+            },
+            Pattern::Owned | Pattern::Immutable => quote! {
+                <Self as #core::default::Default>::default().fill_from_env()
+            },
+        };
+        // A typestate builder comes out of `from_env` in its initial state,
+        // as whether a variable is present is only known at run time.
+        let from_env_impl = quote! {
+            impl<#(#st_impl_params),*> #builder_ident<#(#st_args),*> #where_clause {
+                /// Returns a new builder, with the fields read from environment
+                /// variables set to those that are present.
+                #vis fn from_env() -> #core::result::Result<Self, #error_ty> {
+                    #from_env
+                }
+            }
+        };
+
+        (fill_from_env, from_env_impl)
+    }
+
+    /// Generates the fallible `try_<name>` counterpart of the setter `name`
+    /// of `f`, converting its argument to `ty` and delegating to the setter.
+    pub fn try_setter(&self, f: &BuilderField, name: &Ident, ty: &Type) -> TokenStream {
+        let core = self.core();
+        let vis = &self.vis;
+        let try_name = BuilderField::try_setter_ident(name);
+        let attrs = f.try_setter_attrs(name);
+        let (receiver, ret) = match self.pattern {
+            Pattern::Mutable => (quote! { &mut self }, quote! { &mut Self }),
+            Pattern::Owned => (quote! { self }, quote! { Self }),
+            Pattern::Immutable => (quote! { &self }, quote! { Self }),
+        };
+        quote! {
+            #attrs
+            #vis fn #try_name<__V: #core::convert::TryInto<#ty>>(
+                #receiver,
+                val: __V,
+            ) -> #core::result::Result<#ret, <__V as #core::convert::TryInto<#ty>>::Error> {
+                let val: #ty = #core::convert::TryInto::try_into(val)?;
+                #core::result::Result::Ok(self.#name(val))
+            }
+        }
+    }
+
+    /// Generates the method giving access to the builder of a field marked
+    /// `#[builder(sub_builder)]`, in place of its setter.
+    pub fn sub_builder_accessor(&self, f: &BuilderField) -> Option<TokenStream> {
+        let core = self.core();
+        let sub_builder = f.sub_builder.as_ref()?;
+        let vis = &self.vis;
+        let fi = &f.ident;
+        let attrs = f.setter_attrs(f.setter_docs(&format!(
+            "The builder of `{}`, which is built along with this one.",
+            unraw(fi)
+        )));
+        Some(quote! {
+            #attrs
+            #vis fn #fi(&mut self) -> &mut #sub_builder {
+                self.#fi.get_or_insert_with(#core::default::Default::default)
+            }
+        })
+    }
+
+    /// Generates `<field>_ref`, `is_<field>_set` and `clear_<field>` for every
+    /// field, and `missing_fields` listing the required fields `build()` would
+    /// report as missing. A field with a sub-builder is listed when its own
+    /// builder would fail.
+    ///
+    /// Required fields are only cleared when `clear_required` is set, as a
+    /// typestate builder tracks them in its type.
+    pub fn introspection_fns(&self, clear_required: bool) -> TokenStream {
+        let core = self.core();
+        let alloc = self.alloc();
+        let vis = &self.vis;
+        let accessors = self.fields.iter().filter(|f| !f.receiver).map(|f| {
+            let fi = &f.ident;
+            let name = unraw(fi);
+            let ref_fi = format_ident!("{}_ref", name);
+            let is_set_fi = format_ident!("is_{}_set", name);
+            let clear_fi = format_ident!("clear_{}", name);
+            let ty = match (&f.sub_builder, &f.wrapper_ty) {
+                (Some(sub_builder), _) => quote! { #sub_builder },
+                (None, FieldWrapperType::Option(inner)) => quote! { #inner },
+                (None, _) => {
+                    let fty = f.ty;
+                    quote! { #fty }
+                }
+            };
+            let cfgs = f.cfg_attrs();
+            let (ref_doc, is_set_doc, clear_doc) = (
+                format!("The value `{}` is set to, if any.", name),
+                format!("Whether `{}` is set.", name),
+                format!("Unsets `{}`.", name),
+            );
+            let clear = (clear_required || !self.is_required(f)).then(|| {
+                quote! {
+                    #[doc = #clear_doc]
+                    #cfgs
+                    #vis fn #clear_fi(&mut self) {
+                        self.#fi = #core::option::Option::None;
+                    }
+                }
+            });
+            quote! {
+                #[doc = #ref_doc]
+                #cfgs
+                #vis fn #ref_fi(&self) -> #core::option::Option<&#ty> {
+                    self.#fi.as_ref()
+                }
+
+                #[doc = #is_set_doc]
+                #cfgs
+                #vis fn #is_set_fi(&self) -> bool {
+                    self.#fi.is_some()
+                }
+
+                #clear
+            }
+        });
+
+        let checks = self.fields.iter().filter(|f| !f.receiver).filter_map(|f| {
+            let fi = &f.ident;
+            let name = unraw(fi);
+            let missing = match &f.sub_builder {
+                Some(_) if self.fallback(f).is_some() => quote! {
+                    matches!(&self.#fi, #core::option::Option::Some(builder)
+                        if !builder.missing_fields().is_empty())
+                },
+                Some(sub_builder) => quote! {
+                    match &self.#fi {
+                        #core::option::Option::Some(builder) => !builder.missing_fields().is_empty(),
+                        #core::option::Option::None => {
+                            !<#sub_builder as #core::default::Default>::default()
+                                .missing_fields()
+                                .is_empty()
+                        }
+                    }
+                },
+                None if self.is_required(f) => quote! { self.#fi.is_none() },
+                None => return None,
+            };
+            let cfgs = f.cfg_attrs();
+            Some(quote! {
+                #cfgs
+                if #missing {
+                    missing.push(#name);
+                }
+            })
+        });
+
+        quote! {
+            #(#accessors)*
+
+            /// Required fields that are not set yet, in declaration order.
+            #vis fn missing_fields(&self) -> #alloc::vec::Vec<&'static str> {
+                let mut missing = #alloc::vec::Vec::new();
+                #(#checks)*
+                missing
+            }
+        }
+    }
+
+    /// Generates `build()` and the hidden `__build()` it calls, which a
+    /// containing builder uses to get at `<Builder>Error` when `build()`
+    /// returns a user-defined error type.
+    ///
+    /// A containing builder calls it through the hidden `__sub_build()`,
+    /// which takes the builder by reference, and converts values back with
+    /// the hidden `__sub_from()`. Builders whose setters do not change them
+    /// in place have ones that cannot be called, see `sub_builder_trait`.
+    pub fn build_fns(
+        &self,
+        receiver: TokenStream,
+        bounds: &[TokenStream],
+        body: TokenStream,
+    ) -> TokenStream {
+        let core = self.core();
+        let vis = &self.vis;
+        let build_fn = &self.build_fn;
+        let st_ty = self.st_ty();
+        let error_ident = &self.error_ident;
+        let error_ty = &self.error_ty;
+        let allow_deprecated = self.allow_deprecated();
+        // A function builder's type guarantees that every argument is set,
+        // so `__build()` cannot fail.
+        let build = match &self.call {
+            Some(FnCall {
+                qualifiers,
+                output,
+                body,
+            }) => quote! {
+                /// Calls the function with the arguments set on the builder.
+                #vis #qualifiers fn #build_fn(#receiver) #output
+                where
+                    #(#bounds,)*
+                {
+                    let __args = match self.__build() {
+                        #core::result::Result::Ok(args) => args,
+                        #core::result::Result::Err(e) => #core::panic!("{}", e),
+                    };
+                    #body
+                }
+            },
+            None => quote! {
+                /// Builds the value, failing if a required field is not set
+                /// or the value is not valid.
+                #vis fn #build_fn(#receiver) -> #core::result::Result<#st_ty, #error_ty>
+                where
+                    #(#bounds,)*
+                {
+                    self.__build().map_err(#core::convert::From::from)
+                }
+            },
+        };
+        let sub_build = match (&self.call, self.pattern) {
+            (Some(_), _) => quote! {},
+            (None, Pattern::Owned | Pattern::Immutable) => {
+                let sub_builder_trait = self.sub_builder_trait_ident();
+                // Unbounded, as `__sub_build()` already reports the error.
+                let sub_from = self.round_trip.then(|| {
+                    quote! {
+                        #[doc(hidden)]
+                        pub fn __sub_from(_: #st_ty) -> Self {
+                            #core::unreachable!()
+                        }
+                    }
+                });
+                quote! {
+                    #[doc(hidden)]
+                    pub fn __sub_build<__B: #sub_builder_trait>(
+                        _: &__B,
+                    ) -> #core::result::Result<#st_ty, #error_ident> {
+                        #core::unreachable!()
+                    }
+
+                    #sub_from
+                }
+            }
+            (None, Pattern::Mutable) => {
+                let sub_from = self.round_trip.then(|| {
+                    quote! {
+                        #[doc(hidden)]
+                        pub fn __sub_from(value: #st_ty) -> Self {
+                            #core::convert::From::from(value)
+                        }
+                    }
+                });
+                quote! {
+                    #[doc(hidden)]
+                    pub fn __sub_build(
+                        builder: &Self,
+                    ) -> #core::result::Result<#st_ty, #error_ident>
+                    where
+                        #(#bounds,)*
+                    {
+                        builder.__build()
+                    }
+
+                    #sub_from
+                }
+            }
+        };
+        quote! {
+            #build
+
+            #[doc(hidden)]
+            #allow_deprecated
+            pub fn __build(#receiver) -> #core::result::Result<#st_ty, #error_ident>
+            where
+                #(#bounds,)*
+            {
+                #body
+            }
+
+            #sub_build
+        }
+    }
+
+    fn sub_builder_trait_ident(&self) -> Ident {
+        format_ident!("__{}SubBuilder", self.builder_ident)
+    }
+
+    /// Generates the trait bounding the `__sub_build()` of a builder whose
+    /// setters do not change it in place, which nothing implements. Owned
+    /// setters cannot be called through the `&mut` a containing builder hands
+    /// out, and immutable ones would return a copy that is thrown away, so
+    /// using such a builder as a sub-builder fails with this trait's message.
+    pub fn sub_builder_trait(&self) -> TokenStream {
+        if self.call.is_some() || self.pattern == Pattern::Mutable {
+            return quote! {};
+        }
+        let vis = &self.vis;
+        let sub_builder_trait = self.sub_builder_trait_ident();
+        let message = format!(
+            "`{}` cannot be a sub-builder, as its setters do not change it in place",
+            self.builder_ident
+        );
+        let note = format!(
+            "the setters of a sub-builder are called through `&mut`, which needs \
+             `{}` to have `#[builder(pattern = \"mutable\")]` and no `typestate`",
+            self.st_ident
+        );
+        quote! {
+            #[doc(hidden)]
+            #[diagnostic::on_unimplemented(
+                message = #message,
+                label = "marked `#[builder(sub_builder)]`",
+                note = #note,
+            )]
+            #vis trait #sub_builder_trait {}
+        }
+    }
+
+    /// Generates the method on the built type returning a new builder.
+    pub fn entry_impl(&self) -> TokenStream {
+        let entry_fn = match &self.entry_fn {
+            Some(entry_fn) => entry_fn,
+            None => return quote! {},
+        };
+        let core = self.core();
+        let st_ident = self.st_ident;
+        let builder_ident = &self.builder_ident;
+        let vis = &self.vis;
+        let st_impl_params = impl_params(self.generics);
+        let st_args = generic_args(self.generics);
+        let where_clause = &self.generics.where_clause;
+        let doc = format!("Returns a new [`{}`] with nothing set.", builder_ident);
+        quote! {
+            impl<#(#st_impl_params),*> #st_ident<#(#st_args),*> #where_clause {
+                #[doc = #doc]
+                #vis fn #entry_fn() -> #builder_ident<#(#st_args),*> {
+                    #core::default::Default::default()
+                }
+            }
+        }
+    }
+
+    /// Generates `Default` for the builder, returning it with nothing set.
+    pub fn default_impl(&self) -> TokenStream {
+        let core = self.core();
+        let builder_ident = &self.builder_ident;
+        let st_impl_params = impl_params(self.generics);
+        let st_args = generic_args(self.generics);
+        let where_clause = &self.generics.where_clause;
+        let storage_defaults = self.storage_defaults();
+        quote! {
+            impl<#(#st_impl_params),*> #core::default::Default for #builder_ident<#(#st_args),*> #where_clause {
+                fn default() -> Self {
+                    #builder_ident {
+                        #(#storage_defaults,)*
+                        __phantom: #core::marker::PhantomData,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reports methods of the builder that would have the same name, as
+    /// setters named after fields and `each` setters can clash with each
+    /// other or with the builder's own methods. Each clash is reported on the
+    /// later method, with how to rename it.
+    ///
+    /// Required fields only have `clear_<field>` with `clear_required`, as
+    /// with `introspection_fns`.
+    pub fn check_method_names(&self, clear_required: bool) -> Result<(), syn::Error> {
+        const RENAME_FIELD: &str = "rename the field with `#[builder(name = \"...\")]`";
+        const RENAME_EACH: &str = "rename it with `each = \"...\"`";
+
+        // The name, what the method is and how to rename it.
+        let mut methods = vec![(
+            self.build_fn.clone(),
+            format!("the `{}` method", self.build_fn),
+            "rename it with `#[builder(build_fn = \"...\")]`",
+        )];
+        for name in ["merge", "missing_fields"] {
+            let ident = format_ident!("{}", name);
+            methods.push((ident, format!("the `{}` method", name), RENAME_FIELD));
+        }
+        if self.fields.iter().any(|f| f.env.is_some()) {
+            for name in ["fill_from_env", "from_env"] {
+                let ident = format_ident!("{}", name);
+                methods.push((ident, format!("the `{}` method", name), RENAME_FIELD));
+            }
+        }
+
+        for f in self.fields.iter().filter(|f| !f.receiver) {
+            let fi = &f.ident;
+            let name = unraw(fi);
+            // Generated names point at the field.
+            let method = |mut ident: Ident, what: &str, fix| {
+                ident.set_span(fi.span());
+                (ident, format!("{} of `{}`", what, name), fix)
+            };
+            if f.sub_builder.is_some() {
+                methods.push(method(fi.clone(), "the sub-builder accessor", RENAME_FIELD));
+            } else if !f.each_shadows_setter() {
+                let setter_fi = f.setter_ident.clone();
+                methods.push(method(setter_fi, "the setter", RENAME_FIELD));
+                if f.setter.try_into {
+                    let try_fi = BuilderField::try_setter_ident(&f.setter_ident);
+                    methods.push(method(try_fi, "the `try_` setter", RENAME_FIELD));
+                }
+            }
+            if f.each.is_some() {
+                let extend_fi = format_ident!("extend_{}", name);
+                methods.push(method(extend_fi, "the `extend_` method", RENAME_FIELD));
+            }
+            let ref_fi = format_ident!("{}_ref", name);
+            methods.push(method(ref_fi, "the `_ref` accessor", RENAME_FIELD));
+            let is_set_fi = format_ident!("is_{}_set", name);
+            methods.push(method(is_set_fi, "the `is_*_set` accessor", RENAME_FIELD));
+            if clear_required || !self.is_required(f) {
+                let clear_fi = format_ident!("clear_{}", name);
+                methods.push(method(clear_fi, "the `clear_` method", RENAME_FIELD));
+            }
+            // Listed last, so that they are the ones to rename when they
+            // clash with the field's other methods.
+            if let Some(each) = &f.each {
+                methods.push((
+                    each.name.clone(),
+                    format!("the `each` setter of `{}`", name),
+                    RENAME_EACH,
+                ));
+                if f.setter.try_into {
+                    let mut try_each = BuilderField::try_setter_ident(&each.name);
+                    try_each.set_span(each.name.span());
+                    methods.push((
+                        try_each,
+                        format!("the `try_` `each` setter of `{}`", name),
+                        RENAME_EACH,
+                    ));
+                }
+            }
+        }
+
+        let mut errors = Errors::default();
+        for (i, (ident, what, fix)) in methods.iter().enumerate() {
+            let earlier = methods[..i].iter().find(|(other, _, _)| other == ident);
+            if let Some((_, other_what, _)) = earlier {
+                errors.push(syn::Error::new(
+                    ident.span(),
+                    format!(
+                        "{} has the same name `{}` as {}, {}",
+                        what,
+                        unraw(ident),
+                        other_what,
+                        fix
+                    ),
+                ));
+            }
+        }
+        errors.finish()
+    }
+
+    /// Generates the builder, with setters following the chosen pattern.
+    pub fn expand(&self) -> TokenStream {
+        let core = self.core();
+        let builder_ident = &self.builder_ident;
+        let vis = &self.vis;
+        let generics = self.generics;
+        let st_impl_params = impl_params(generics);
+        let st_args = generic_args(generics);
+        let st_phantom = phantom_params(generics, &core);
+        let where_clause = &generics.where_clause;
+
+        let storage = self.storage();
+        let builder_attrs = self.builder_attrs();
+        // Absent keys leave their field unset. The bounds on every field
+        // replace those serde would infer, so that a builder with fields that
+        // cannot be deserialized lacks the impl rather than failing to compile.
+        let (deserialize, unset_if_absent, skip) = match self.deserialize {
+            true => (
+                {
+                    let bound = self
+                        .fields
+                        .iter()
+                        .map(|f| format!("{}: ::serde::Deserialize<'de>", self.storage_ty(f)))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    quote! {
+                        #[derive(::serde::Deserialize)]
+                        #[serde(bound(deserialize = #bound))]
+                    }
+                },
+                quote! { #[serde(default)] },
+                quote! { #[serde(skip)] },
+            ),
+            false => (quote! {}, quote! {}, quote! {}),
+        };
+
+        let methods = self
+            .fields
+            .iter()
+            .filter(|f| !f.each_shadows_setter())
+            .map(|f| {
+                if let Some(accessor) = self.sub_builder_accessor(f) {
+                    return accessor;
+                }
+                self.field_setter(f)
+            });
+
+        let each_methods = self.fields.iter().filter_map(|f| self.each_methods(f));
+        let introspection_fns = self.introspection_fns(true);
+        let merge_fns = self.merge_fns();
+        let (fill_from_env, from_env_impl) = self.env_fns();
+
+        // The owned builder is consumed by `build()`, so fields are moved out
+        // of it. The others clone every field, which for generic structs only
+        // type checks if the field types are known to be `Clone`. Sub-builders
+        // are built from a reference either way.
+        let (build_receiver, build_body, mut build_bounds) = match self.pattern {
+            Pattern::Owned => (
+                quote! { self },
+                self.build_body(|fi| quote! { self.#fi }),
+                Vec::new(),
+            ),
+            Pattern::Mutable | Pattern::Immutable => (
+                quote! { &self },
+                self.build_body(|fi| quote! { self.#fi.clone() }),
+                self.fields
+                    .iter()
+                    .filter(|f| f.sub_builder.is_none())
+                    .map(|f| {
+                        let fty = f.ty;
+                        quote! { #fty: #core::clone::Clone }
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        };
+        build_bounds.extend(self.build_bounds());
+        let build_fns = self.build_fns(build_receiver, &build_bounds, build_body);
+
+        let default_impl = self.default_impl();
+        let round_trip = self.round_trip(&quote! { #builder_ident<#(#st_args),*> });
+        let error_type = self.error_type();
+        let sub_builder_trait = self.sub_builder_trait();
+
+        let entry_impl = self.entry_impl();
+
+        quote! {
+            #entry_impl
+
+            #builder_attrs
+            #deserialize
+            #vis struct #builder_ident #generics #where_clause {
+                #(#unset_if_absent #storage,)*
+                #skip
+                __phantom: #core::marker::PhantomData<(#(#st_phantom,)*)>,
+            }
+
+            impl<#(#st_impl_params),*> #builder_ident<#(#st_args),*> #where_clause {
+                #(#methods)*
+                #(#each_methods)*
+                #introspection_fns
+                #merge_fns
+                #fill_from_env
+                #build_fns
+            }
+
+            #from_env_impl
+            #default_impl
+            #round_trip
+            #error_type
+            #sub_builder_trait
+        }
+    }
+}
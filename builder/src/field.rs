@@ -0,0 +1,688 @@
+use crate::attrs::{field_options, FieldDefault, Merge, SetterOptions, StructOptions};
+use crate::naming::unraw;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{
+    parse_quote, AngleBracketedGenericArguments, Attribute, BareFnArg, Expr, ExprCast, Field,
+    GenericArgument, Ident, Index, Member, Meta, Pat, PatIdent, PatType, Path, PathArguments,
+    ReturnType, Type, TypeBareFn, TypeGroup, TypeParen, TypePath,
+};
+
+pub(crate) enum FieldWrapperType<'a> {
+    None,
+    /// An `Option` of the contained type.
+    Option(Box<Type>),
+    /// A standard library collection that can be filled one item at a time.
+    Collection(CollectionItem<'a>),
+}
+
+/// What an `each` setter adds to its collection.
+#[derive(Clone, Copy)]
+pub(crate) enum CollectionItem<'a> {
+    /// A single element, as for `Vec<T>`, `VecDeque<T>` or `HashSet<T>`.
+    Value(&'a Type),
+    /// A key and its value, as for `HashMap<K, V>` and `BTreeMap<K, V>`.
+    Entry(&'a Type, &'a Type),
+    /// Anything the collection implements `Extend` for. Used for collections
+    /// opted in with `each(name = "...", collection)`, whose item type the
+    /// macro cannot know.
+    Any,
+}
+
+/// The `each` setter of a collection field.
+pub(crate) struct Each<'a> {
+    pub name: Ident,
+    pub item: CollectionItem<'a>,
+    pub merge: Merge,
+}
+
+/// The setter of a field given `setter(with = ...)`, which takes the
+/// arguments of the `with` function and stores what it returns.
+pub(crate) struct SetterWith {
+    pub params: Vec<(Ident, Type)>,
+    /// The value stored, computed from the parameters.
+    pub value: TokenStream,
+    /// Error type of a `with` function returning a `Result`, which the setter
+    /// returns too.
+    pub error: Option<Type>,
+}
+
+impl SetterWith {
+    /// Classifies `with`, which is either a closure with typed arguments, a
+    /// function path cast to a function pointer type giving its arguments,
+    /// as in `parse_duration as fn(&str) -> Duration`, or a plain function
+    /// path transforming a `setter_ty`.
+    fn new(with: &Expr, setter_ty: &Type, fallible: bool) -> Result<Self, syn::Error> {
+        let (params, func, output) = match with {
+            Expr::Closure(closure) => {
+                let params = closure
+                    .inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, input)| match input {
+                        Pat::Type(PatType { pat, ty, .. }) => {
+                            let ident = match &**pat {
+                                Pat::Ident(PatIdent { ident, .. }) => ident.clone(),
+                                _ => format_ident!("arg{}", i),
+                            };
+                            Ok((ident, (**ty).clone()))
+                        }
+                        _ => Err(syn::Error::new_spanned(
+                            input,
+                            "closure arguments need a type, such as `|s: &str|`",
+                        )),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (params, quote! { (#closure) }, closure.output.clone())
+            }
+            Expr::Cast(ExprCast { expr, ty, .. }) if matches!(**expr, Expr::Path(_)) => match &**ty
+            {
+                Type::BareFn(TypeBareFn { inputs, output, .. }) => {
+                    let params = inputs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, BareFnArg { name, ty, .. })| {
+                            let ident = match name {
+                                Some((ident, _)) => ident.clone(),
+                                None => format_ident!("arg{}", i),
+                            };
+                            (ident, ty.clone())
+                        })
+                        .collect();
+                    (params, quote! { #expr }, output.clone())
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "expected a function pointer type, such as `fn(&str) -> Duration`",
+                    ))
+                }
+            },
+            Expr::Path(path) => (
+                vec![(format_ident!("val"), setter_ty.clone())],
+                quote! { #path },
+                ReturnType::Default,
+            ),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    with,
+                    "expected a closure or the path of a function",
+                ))
+            }
+        };
+
+        let error = match &output {
+            ReturnType::Type(_, ty) => result_error(ty)?,
+            ReturnType::Default => None,
+        };
+        // Otherwise the setter would silently change its return type.
+        match (&error, fallible) {
+            (Some(_), false) => {
+                return Err(syn::Error::new_spanned(
+                    with,
+                    "`with` returns a `Result`, add `setter(fallible)` for the setter \
+                     to return its error",
+                ))
+            }
+            (None, true) => {
+                return Err(syn::Error::new_spanned(
+                    with,
+                    "`setter(fallible)` needs a `with` function returning a `Result`",
+                ))
+            }
+            _ => {}
+        }
+
+        let args = params.iter().map(|(ident, _)| ident);
+        // Both checks go through a local trait so that a mismatch is reported
+        // at `with`, naming what was expected rather than the builder's
+        // internals.
+        let value = match (with, &error) {
+            (Expr::Path(_), _) => quote_spanned! {with.span()=> {
+                #[diagnostic::on_unimplemented(
+                    message = "`with` is a `{Self}`, but a plain path has to be a `fn({T}) -> {T}`",
+                    label = "called with the field's type",
+                    note = "a function taking other arguments has to be cast to their types, \
+                            as in `parse_duration as fn(&str) -> {T}`"
+                )]
+                trait __WithPath<T> {
+                    fn __call(self, val: T) -> T;
+                }
+                impl<T> __WithPath<T> for fn(T) -> T {
+                    fn __call(self, val: T) -> T {
+                        self(val)
+                    }
+                }
+                fn __reify<A, R>(f: fn(A) -> R) -> fn(A) -> R {
+                    f
+                }
+                <_ as __WithPath<#setter_ty>>::__call(__reify(#func), #(#args),*)
+            }},
+            (_, error) => {
+                let call = match error {
+                    Some(_) => quote! { #func(#(#args),*)? },
+                    None => quote! { #func(#(#args),*) },
+                };
+                quote_spanned! {with.span()=> {
+                    #[diagnostic::on_unimplemented(
+                        message = "the `with` function returns `{Self}` rather than `{T}`",
+                        label = "expected `{T}`",
+                        note = "a `with` function returning a `Result` needs its return type \
+                                written out, as in `-> Result<{T}, E>`, and `setter(fallible)`"
+                    )]
+                    trait __With<T> {
+                        fn __with(self) -> T;
+                    }
+                    impl<T> __With<T> for T {
+                        fn __with(self) -> T {
+                            self
+                        }
+                    }
+                    <_ as __With<#setter_ty>>::__with(#call)
+                }}
+            }
+        };
+        Ok(SetterWith {
+            params,
+            value,
+            error,
+        })
+    }
+}
+
+/// The error type of `ty` if it is a `Result`, which has to name it.
+fn result_error(ty: &Type) -> Result<Option<Type>, syn::Error> {
+    let last = match ty {
+        Type::Path(TypePath { qself: None, path }) => path.segments.last(),
+        _ => None,
+    };
+    match last {
+        Some(last) if last.ident == "Result" => match &type_args(&last.arguments)[..] {
+            [_, error] => Ok(Some((*error).clone())),
+            _ => Err(syn::Error::new_spanned(
+                ty,
+                "expected the error type to be written out, as in `Result<T, E>`",
+            )),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// A struct field as seen by the builder.
+pub(crate) struct BuilderField<'a> {
+    /// Name of the builder's storage for the field.
+    pub ident: Ident,
+    /// Name of the field's setter, which is `ident` unless the struct gives a
+    /// `setter_prefix`.
+    pub setter_ident: Ident,
+    /// How the field is accessed on the built value.
+    pub member: Member,
+    pub ty: &'a Type,
+    pub wrapper_ty: FieldWrapperType<'a>,
+    /// Method adding one item at a time to a collection field.
+    pub each: Option<Each<'a>>,
+    pub default: Option<FieldDefault>,
+    pub setter: SetterOptions,
+    /// Builder of the field's type, held by the builder in place of a value
+    /// for fields marked `#[builder(sub_builder)]`.
+    pub sub_builder: Option<Type>,
+    /// Environment variable `fill_from_env` parses the field from.
+    pub env: Option<String>,
+    /// Attributes for the builder's storage of the field.
+    pub attrs: Vec<Meta>,
+    /// The field has no storage or setter, and `build()` computes it from its
+    /// default once every other field is known.
+    pub skip: bool,
+    /// The receiver of a method, which its builder is created with and has
+    /// no setter for.
+    pub receiver: bool,
+    pub with: Option<SetterWith>,
+    /// The field's doc comments, which its setters are documented with.
+    pub docs: Vec<&'a Attribute>,
+    /// The field's `#[cfg]` attributes, which everything generated for it
+    /// gets too, save for `where` bounds that cannot have attributes. As
+    /// derive input is configured before the macro sees it, these always
+    /// hold there.
+    pub cfgs: Vec<&'a Attribute>,
+    /// The field's `#[deprecated]` attribute, which its setters get too.
+    pub deprecated: Option<&'a Attribute>,
+}
+
+fn type_args(args: &PathArguments) -> Vec<&Type> {
+    match args {
+        PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }) => args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether `path` names the standard library type `name`, either by its bare
+/// name or by one of the full `modules` paths it can be reached under, with or
+/// without a leading `::`.
+fn is_std_type(path: &Path, name: &str, modules: &[&str]) -> bool {
+    let segments = path
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .collect::<Vec<_>>();
+    match segments.split_last() {
+        Some((last, [])) => last == name && path.leading_colon.is_none(),
+        Some((last, module)) => last == name && modules.contains(&&module.join("::")[..]),
+        None => false,
+    }
+}
+
+const VALUE_COLLECTIONS: &[(&str, &[&str])] = &[
+    ("Vec", &["std::vec", "alloc::vec"]),
+    (
+        "VecDeque",
+        &[
+            "std::collections",
+            "std::collections::vec_deque",
+            "alloc::collections",
+            "alloc::collections::vec_deque",
+        ],
+    ),
+    (
+        "LinkedList",
+        &[
+            "std::collections",
+            "std::collections::linked_list",
+            "alloc::collections",
+            "alloc::collections::linked_list",
+        ],
+    ),
+    (
+        "BinaryHeap",
+        &[
+            "std::collections",
+            "std::collections::binary_heap",
+            "alloc::collections",
+            "alloc::collections::binary_heap",
+        ],
+    ),
+    (
+        "BTreeSet",
+        &[
+            "std::collections",
+            "std::collections::btree_set",
+            "alloc::collections",
+            "alloc::collections::btree_set",
+        ],
+    ),
+    (
+        "HashSet",
+        &["std::collections", "std::collections::hash_set"],
+    ),
+];
+
+const ENTRY_COLLECTIONS: &[(&str, &[&str])] = &[
+    (
+        "BTreeMap",
+        &[
+            "std::collections",
+            "std::collections::btree_map",
+            "alloc::collections",
+            "alloc::collections::btree_map",
+        ],
+    ),
+    (
+        "HashMap",
+        &["std::collections", "std::collections::hash_map"],
+    ),
+];
+
+fn wrapper_type(ty: &Type) -> FieldWrapperType<'_> {
+    // Anything that is not a plain path, such as the references a struct with
+    // lifetime parameters holds, is stored as is.
+    let path = match ty {
+        // Types passed through `macro_rules!` arrive wrapped in a group.
+        Type::Group(TypeGroup { elem, .. }) | Type::Paren(TypeParen { elem, .. }) => {
+            return wrapper_type(elem)
+        }
+        Type::Path(TypePath { qself: None, path }) => path,
+        _ => return FieldWrapperType::None,
+    };
+
+    // Without type information, aliases and other types that happen to be
+    // called `Option` or `Vec` cannot be told apart from the real ones. Only
+    // the names the standard library types are reachable under are
+    // recognized, and `#[builder(optional)]` covers aliases of `Option`.
+    let last = match path.segments.last() {
+        Some(last) => last,
+        None => return FieldWrapperType::None,
+    };
+    let args = type_args(&last.arguments);
+    if is_std_type(path, "Option", &["std::option", "core::option"]) {
+        if let [inner_ty] = args[..] {
+            return FieldWrapperType::Option(Box::new(inner_ty.clone()));
+        }
+    }
+    for (name, modules) in VALUE_COLLECTIONS {
+        if let (true, [value, ..]) = (is_std_type(path, name, modules), &args[..]) {
+            return FieldWrapperType::Collection(CollectionItem::Value(value));
+        }
+    }
+    for (name, modules) in ENTRY_COLLECTIONS {
+        if let (true, [key, value, ..]) = (is_std_type(path, name, modules), &args[..]) {
+            return FieldWrapperType::Collection(CollectionItem::Entry(key, value));
+        }
+    }
+    FieldWrapperType::None
+}
+
+/// The builder generated for `ty`, which must be a struct deriving `Builder`:
+/// `ServerBuilder` for `Server` or `config::ServerBuilder<T>` for
+/// `config::Server<T>`.
+fn sub_builder_type(ty: &Type) -> Result<Type, syn::Error> {
+    match ty {
+        Type::Group(TypeGroup { elem, .. }) | Type::Paren(TypeParen { elem, .. }) => {
+            sub_builder_type(elem)
+        }
+        Type::Path(TypePath { qself: None, path })
+            if !matches!(
+                wrapper_type(ty),
+                FieldWrapperType::Option(_) | FieldWrapperType::Collection(_)
+            ) =>
+        {
+            let mut path = path.clone();
+            if let Some(last) = path.segments.last_mut() {
+                let span = last.ident.span();
+                last.ident = format_ident!("{}Builder", unraw(&last.ident), span = span);
+            }
+            Ok(Type::Path(TypePath { qself: None, path }))
+        }
+        _ => Err(syn::Error::new_spanned(
+            ty,
+            "`sub_builder` needs a field whose type is a struct deriving `Builder`",
+        )),
+    }
+}
+
+impl<'a> BuilderField<'a> {
+    /// Classifies the field at position `index` of its struct or variant.
+    /// Tuple fields are named `_0`, `_1`, ... in the builder unless renamed
+    /// with `#[builder(name = "...")]`. Setter options given on the struct
+    /// apply in addition to the field's own.
+    pub fn new(
+        f: &'a Field,
+        index: usize,
+        struct_options: &StructOptions,
+    ) -> Result<Self, syn::Error> {
+        let struct_setter = struct_options.setter;
+        let options = field_options(&f.attrs)?;
+
+        let member = match &f.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        };
+        let ident = match (options.name, &f.ident) {
+            (Some(name), _) => name,
+            (None, Some(ident)) => ident.clone(),
+            (None, None) => format_ident!("_{}", index),
+        };
+
+        let wrapper_ty = match options.optional {
+            // Whatever the alias stands for, it iterates over the value it
+            // may hold.
+            true => {
+                let ty = &f.ty;
+                FieldWrapperType::Option(Box::new(match struct_options.no_std {
+                    Some(_) => parse_quote! { <#ty as ::core::iter::IntoIterator>::Item },
+                    None => parse_quote! { <#ty as std::iter::IntoIterator>::Item },
+                }))
+            }
+            false => wrapper_type(&f.ty),
+        };
+        let each = match &options.each {
+            Some(each) => {
+                let item = match wrapper_ty {
+                    _ if each.collection => CollectionItem::Any,
+                    FieldWrapperType::Collection(item) => item,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            &each.name,
+                            "`each` needs a collection such as `Vec<T>`, `HashSet<T>` or \
+                             `HashMap<K, V>`; for another type implementing `Default` and \
+                             `Extend`, use `each(name = \"...\", collection)`",
+                        ))
+                    }
+                };
+                Some(Each {
+                    name: each.name.clone(),
+                    item,
+                    // Appending needs the collection's item type, which is not
+                    // known for opted-in collections unless asked for.
+                    merge: match (options.merge, item) {
+                        (Some(merge), _) => merge,
+                        (None, CollectionItem::Any) => Merge::Replace,
+                        (None, _) => Merge::Append,
+                    },
+                })
+            }
+            None => None,
+        };
+
+        // A map's `each` setter takes a key and a value, which a single
+        // `TryInto` conversion cannot fill, and an opted-in collection's item
+        // type is unknown, its `each` setter taking any item the collection
+        // can be extended with. Given on the struct, `try_into` leaves these
+        // fields out rather than being rejected.
+        let item = each.as_ref().map(|each| each.item);
+        match item {
+            Some(CollectionItem::Entry(..)) if options.setter.try_into => {
+                return Err(syn::Error::new_spanned(
+                    &f.ty,
+                    "`try_into` is not supported for maps, whose `each` setter takes a key \
+                     and a value",
+                ))
+            }
+            Some(CollectionItem::Any) if options.setter.into || options.setter.try_into => {
+                return Err(syn::Error::new_spanned(
+                    &f.ty,
+                    "`into` and `try_into` need the item type, which \
+                     `each(name = \"...\", collection)` leaves unknown",
+                ))
+            }
+            _ => {}
+        }
+        let try_into = struct_setter.try_into
+            && !matches!(item, Some(CollectionItem::Entry(..) | CollectionItem::Any));
+
+        let sub_builder = match options.sub_builder {
+            true if options.each.is_some() || options.optional => {
+                return Err(syn::Error::new_spanned(
+                    &f.ty,
+                    "`sub_builder` cannot be combined with `each` or `optional`",
+                ))
+            }
+            true => Some(sub_builder_type(&f.ty)?),
+            false => None,
+        };
+
+        // With a prefix, every field that holds a single value is read from
+        // the environment.
+        if let (Some(var), true) = (&options.env, each.is_some() || sub_builder.is_some()) {
+            return Err(syn::Error::new_spanned(
+                var,
+                "`env` cannot be combined with `each` or `sub_builder`",
+            ));
+        }
+        if let (Some(var), Some(_)) = (&options.env, &struct_options.no_std) {
+            return Err(syn::Error::new_spanned(
+                var,
+                "`env` reads environment variables, which needs `std`",
+            ));
+        }
+        // The value read would have to go through the `with` function, whose
+        // arguments need not be parsed from a string.
+        if let (Some(var), Some(_)) = (&options.env, &options.with) {
+            return Err(syn::Error::new_spanned(
+                var,
+                "`env` cannot be combined with `setter(with = ...)`",
+            ));
+        }
+        let env = match (options.env, &struct_options.env_prefix) {
+            (Some(var), _) => Some(var.value()),
+            (None, Some(_))
+                if each.is_some()
+                    || sub_builder.is_some()
+                    || options.with.is_some()
+                    || options.skip =>
+            {
+                None
+            }
+            (None, Some(prefix)) => Some(format!("{}{}", prefix, unraw(&ident).to_uppercase())),
+            (None, None) => None,
+        };
+
+        // The `with` function takes the place of the setter's conversions.
+        let with = match &options.with {
+            Some(with) => {
+                if options.setter.into || options.setter.try_into {
+                    return Err(syn::Error::new_spanned(
+                        with,
+                        "`with` cannot be combined with `into` or `try_into`",
+                    ));
+                }
+                if each.is_some() || sub_builder.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        with,
+                        "`with` cannot be combined with `each` or `sub_builder`",
+                    ));
+                }
+                let setter_ty = match &wrapper_ty {
+                    FieldWrapperType::Option(ty) => ty,
+                    _ => &f.ty,
+                };
+                Some(SetterWith::new(with, setter_ty, options.setter.fallible)?)
+            }
+            None if options.setter.fallible => {
+                return Err(syn::Error::new_spanned(
+                    &f.ty,
+                    "`setter(fallible)` needs a `with` function returning a `Result`",
+                ))
+            }
+            None => None,
+        };
+
+        let setter_ident = match &struct_options.setter_prefix {
+            Some(prefix) => format_ident!("{}{}", prefix, unraw(&ident)),
+            None => ident.clone(),
+        };
+
+        Ok(BuilderField {
+            setter_ident,
+            ident,
+            member,
+            ty: &f.ty,
+            wrapper_ty,
+            each,
+            default: options.default,
+            setter: SetterOptions {
+                into: with.is_none() && (options.setter.into || struct_setter.into),
+                try_into: with.is_none() && (options.setter.try_into || try_into),
+                fallible: with.is_some() && options.setter.fallible,
+            },
+            sub_builder,
+            env,
+            attrs: options.field_attrs,
+            skip: options.skip,
+            receiver: false,
+            with,
+            docs: f.attrs.iter().filter(|a| a.path.is_ident("doc")).collect(),
+            cfgs: f.attrs.iter().filter(|a| a.path.is_ident("cfg")).collect(),
+            deprecated: f.attrs.iter().find(|a| a.path.is_ident("deprecated")),
+        })
+    }
+
+    /// Whether the `each` method has the name of the field's setter, in which
+    /// case it replaces the whole-field setter.
+    pub fn each_shadows_setter(&self) -> bool {
+        matches!(&self.each, Some(each) if each.name == self.setter_ident)
+    }
+
+    /// Name of the fallible variant of the setter called `setter`.
+    pub fn try_setter_ident(setter: &Ident) -> Ident {
+        format_ident!("try_{}", unraw(setter))
+    }
+
+    /// Name of the local holding the field's value in `build()`, which is the
+    /// struct's own name for it, so that `default` expressions can use it.
+    pub fn local(&self) -> &Ident {
+        match &self.member {
+            Member::Named(ident) => ident,
+            Member::Unnamed(_) => &self.ident,
+        }
+    }
+
+    /// The field's `#[cfg]` attributes.
+    pub fn cfg_attrs(&self) -> TokenStream {
+        let cfgs = &self.cfgs;
+        quote! { #(#cfgs)* }
+    }
+
+    /// Attributes of a setter of the field documented by `docs`, which also
+    /// gets the field's `#[cfg]` and `#[deprecated]` attributes.
+    pub fn setter_attrs(&self, docs: TokenStream) -> TokenStream {
+        let cfgs = &self.cfgs;
+        let deprecated = &self.deprecated;
+        quote! {
+            #docs
+            #deprecated
+            #(#cfgs)*
+        }
+    }
+
+    /// The field's docs, or `fallback` if it has none.
+    pub fn setter_docs(&self, fallback: &str) -> TokenStream {
+        let docs = &self.docs;
+        match docs.is_empty() {
+            true => quote! { #[doc = #fallback] },
+            false => quote! { #(#docs)* },
+        }
+    }
+
+    /// Documents a method acting on the field with `summary`, followed by
+    /// the field's own docs.
+    pub fn method_docs(&self, summary: &str) -> TokenStream {
+        let docs = &self.docs;
+        match docs.is_empty() {
+            true => quote! { #[doc = #summary] },
+            false => quote! {
+                #[doc = #summary]
+                #[doc = ""]
+                #(#docs)*
+            },
+        }
+    }
+
+    /// Attributes of the `try_` variant of the setter called `setter`, which
+    /// calls it even when it is deprecated.
+    pub fn try_setter_attrs(&self, setter: &Ident) -> TokenStream {
+        let doc = format!(
+            "Like [`{0}`](Self::{0}), but taking anything with a `TryInto` \
+             conversion, and returning its error when it fails.",
+            unraw(setter)
+        );
+        let attrs = self.setter_attrs(quote! { #[doc = #doc] });
+        match self.deprecated {
+            Some(_) => quote! { #attrs #[allow(deprecated)] },
+            None => attrs,
+        }
+    }
+
+    /// The type taken by the field's setter.
+    pub fn setter_ty(&self) -> &Type {
+        match &self.wrapper_ty {
+            FieldWrapperType::Option(ty) => ty,
+            _ => self.ty,
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use crate::attrs::{struct_options, Pattern, StructOptions};
+use crate::errors::Errors;
+use crate::expand::BuilderContext;
+use crate::generics::{generic_args, impl_params, phantom_params};
+use crate::naming::{to_camel_case, unraw};
+use crate::{builder_fields, typestate};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    parse_quote, Attribute, Field, Fields, FieldsNamed, FnArg, GenericParam, Generics, Ident,
+    ImplItem, Item, Lifetime, LifetimeDef, ParenthesizedGenericArguments, Pat, PatIdent, PatType,
+    ReturnType, Signature, Token, Type, TypePath, TypeReference, Visibility, WhereClause,
+};
+
+/// How the builder of a function's arguments calls it, in place of returning
+/// the arguments from `build()`.
+pub(crate) struct FnCall {
+    /// `async` and `unsafe`, as on the function.
+    pub qualifiers: TokenStream,
+    /// The function's return type.
+    pub output: ReturnType,
+    /// Calls the function with the arguments bound as `__args`.
+    pub body: TokenStream,
+}
+
+/// Expands `#[builder]` on a function, or on an impl block some of whose
+/// methods are marked `#[builder]`. Every such function is kept as it is,
+/// and gets a typestate builder of its arguments whose `call()` calls it,
+/// along with `<function>_builder()` returning that builder.
+///
+/// Methods need the attribute on their impl block as well, so that their
+/// builder can be defined outside of it.
+pub(crate) fn expand(args: TokenStream, item: Item) -> Result<TokenStream, syn::Error> {
+    match item {
+        Item::Fn(mut f) => {
+            if let Some(FnArg::Receiver(receiver)) = f.sig.inputs.first() {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "methods need `#[builder]` on their impl block as well",
+                ));
+            }
+            let attrs: Vec<Attribute> = match args.is_empty() {
+                true => Vec::new(),
+                false => vec![parse_quote! { #[builder(#args)] }],
+            };
+            let options = fn_options(&attrs, &args)?;
+            let (entry, builder) = expand_fn(&mut f.sig, &f.vis, None, &options)?;
+            Ok(quote! {
+                #f
+                #entry
+                #builder
+            })
+        }
+        Item::Impl(mut imp) => {
+            if !args.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    args,
+                    "options go on the `#[builder(...)]` of each method",
+                ));
+            }
+            if let Some((_, path, _)) = &imp.trait_ {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "`#[builder]` only applies to inherent impl blocks",
+                ));
+            }
+
+            let self_ty = imp.self_ty.clone();
+            let generics = imp.generics.clone();
+            let mut errors = Errors::default();
+            let mut entries = Vec::new();
+            let mut builders = TokenStream::new();
+            for item in &mut imp.items {
+                let method = match item {
+                    ImplItem::Method(method) => method,
+                    _ => continue,
+                };
+                let attrs = take_builder_attrs(&mut method.attrs);
+                if attrs.is_empty() {
+                    continue;
+                }
+                let spanned = quote! { #(#attrs)* };
+                let options = match errors.check(fn_options(&attrs, &spanned)) {
+                    Some(options) => options,
+                    None => continue,
+                };
+                let imp = Some((&*self_ty, &generics));
+                if let Some((entry, builder)) =
+                    errors.check(expand_fn(&mut method.sig, &method.vis, imp, &options))
+                {
+                    entries.push(ImplItem::Verbatim(entry));
+                    builders.extend(builder);
+                }
+            }
+            errors.finish()?;
+
+            imp.items.extend(entries);
+            Ok(quote! {
+                #imp
+                #builders
+            })
+        }
+        item => Err(syn::Error::new_spanned(
+            item,
+            "`#[builder]` applies to functions and impl blocks",
+        )),
+    }
+}
+
+/// Removes the `#[builder]` attributes from `attrs`, returning them.
+fn take_builder_attrs(attrs: &mut Vec<Attribute>) -> Vec<Attribute> {
+    let (builder, others) = attrs.drain(..).partition(|a| a.path.is_ident("builder"));
+    *attrs = others;
+    builder
+}
+
+/// Parses the options of a function builder, which are those of a struct's
+/// builder that still make sense for arguments. The builder is always a
+/// typestate builder, so that `call()` only compiles once every required
+/// argument is given. Unsupported options are reported on `spanned`.
+fn fn_options(attrs: &[Attribute], spanned: &TokenStream) -> Result<StructOptions, syn::Error> {
+    // A bare `#[builder]` has nothing to parse.
+    let lists = attrs
+        .iter()
+        .filter(|a| !a.tokens.is_empty())
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut options = struct_options(&lists)?;
+    if options.default
+        || options.validate.is_some()
+        || options.error.is_some()
+        || options.pattern.is_some()
+        || options.deserialize.is_some()
+    {
+        return Err(syn::Error::new_spanned(
+            spanned,
+            "`default`, `validate`, `error`, `pattern` and `deserialize` are not supported \
+             on functions",
+        ));
+    }
+    options.typestate = true;
+    Ok(options)
+}
+
+/// Generates the builder of the function with signature `sig`, stripping the
+/// `#[builder]` attributes from its arguments. `imp` gives the type and
+/// generics of the impl block of a method.
+///
+/// Returns the function creating the builder, which goes next to the
+/// function, and the builder itself.
+fn expand_fn(
+    sig: &mut Signature,
+    vis: &Visibility,
+    imp: Option<(&Type, &Generics)>,
+    options: &StructOptions,
+) -> Result<(TokenStream, TokenStream), syn::Error> {
+    let mut errors = Errors::default();
+    let self_ty = imp.map(|(self_ty, _)| self_ty);
+    let mut types = ArgTypes::new(self_ty, Elided::Fresh);
+
+    // The arguments as the fields of a struct, with the receiver of a method
+    // first and named `__receiver`.
+    let mut fields = Punctuated::<Field, Token![,]>::new();
+    let mut receiver = None;
+    let mut receiver_lifetime = None;
+    for arg in &mut sig.inputs {
+        let (ident, mut ty, attrs) = match arg {
+            FnArg::Receiver(r) => {
+                let lifetime = r.reference.as_ref().map(|(_, lifetime)| match lifetime {
+                    Some(lifetime) => lifetime.clone(),
+                    None => types.fresh(),
+                });
+                let mutability = &r.mutability;
+                let self_ty = match self_ty {
+                    Some(self_ty) => self_ty,
+                    None => {
+                        return Err(syn::Error::new_spanned(
+                            r,
+                            "`self` is only accepted on methods",
+                        ))
+                    }
+                };
+                let ty: Type = match &lifetime {
+                    Some(lifetime) => parse_quote! { &#lifetime #mutability #self_ty },
+                    None => parse_quote! { #self_ty },
+                };
+                receiver_lifetime = lifetime;
+                (format_ident!("__receiver"), ty, Vec::new())
+            }
+            FnArg::Typed(PatType { attrs, pat, ty, .. }) => {
+                // The typestate builder tracks every argument in its type,
+                // which cannot depend on `#[cfg]`.
+                if let Some(cfg) = attrs.iter().find(|a| a.path.is_ident("cfg")) {
+                    errors.push(syn::Error::new_spanned(
+                        cfg,
+                        "`#[cfg]` is not supported on the arguments of a function builder",
+                    ));
+                }
+                let attrs = take_builder_attrs(attrs);
+                match &**pat {
+                    Pat::Ident(PatIdent {
+                        ident,
+                        by_ref: None,
+                        subpat: None,
+                        ..
+                    }) if ident == "self" => (format_ident!("__receiver"), (**ty).clone(), attrs),
+                    Pat::Ident(PatIdent {
+                        ident,
+                        by_ref: None,
+                        subpat: None,
+                        ..
+                    }) => (ident.clone(), (**ty).clone(), attrs),
+                    _ => {
+                        errors.push(syn::Error::new_spanned(
+                            pat,
+                            "expected a named argument, which the builder can have a setter for",
+                        ));
+                        continue;
+                    }
+                }
+            }
+        };
+
+        types.visit_type_mut(&mut ty);
+        if std::mem::take(&mut types.impl_trait) {
+            errors.push(syn::Error::new_spanned(
+                &ty,
+                "`impl Trait` arguments cannot be stored in a builder, \
+                 use a generic parameter instead",
+            ));
+        }
+        if ident == "__receiver" {
+            if let (None, Type::Reference(TypeReference { lifetime, .. })) =
+                (&receiver_lifetime, &ty)
+            {
+                receiver_lifetime = lifetime.clone();
+            }
+            receiver = Some(ty.clone());
+        }
+        fields.push(Field {
+            attrs,
+            vis: Visibility::Inherited,
+            ident: Some(ident),
+            colon_token: Some(Default::default()),
+            ty,
+        });
+    }
+
+    // Elided lifetimes in the return type follow the usual rules: they are
+    // those of `&self`, or of the only lifetime among the arguments.
+    let elided = match (&receiver_lifetime, &types.seen[..]) {
+        (Some(lifetime), _) => Elided::As(lifetime.clone()),
+        (None, [lifetime]) => Elided::As(lifetime.clone()),
+        (None, _) => Elided::Keep,
+    };
+    let mut output = sig.output.clone();
+    ArgTypes::new(self_ty, elided).visit_return_type_mut(&mut output);
+
+    // Generics of the function, with the lifetimes standing for elided ones.
+    let mut fn_generics = sig.generics.clone();
+    ArgTypes::new(self_ty, Elided::Keep).visit_generics_mut(&mut fn_generics);
+    let fresh = types
+        .fresh
+        .into_iter()
+        .map(|lifetime| GenericParam::Lifetime(LifetimeDef::new(lifetime)));
+    fn_generics.params.extend(fresh);
+    let fn_generics = sort_generics(&[&fn_generics]);
+    // The builder's generics also include those of the impl block.
+    let builder_generics = match imp {
+        Some((_, imp_generics)) => sort_generics(&[imp_generics, &fn_generics]),
+        None => fn_generics.clone(),
+    };
+
+    let core = match options.no_std {
+        Some(_) => quote! { ::core },
+        None => quote! { std },
+    };
+    // Every parameter is recorded in an extra skipped field, as the
+    // arguments struct need not use all of them.
+    let phantom = phantom_params(&builder_generics, &core);
+    fields.push(Field {
+        attrs: vec![parse_quote! { #[builder(skip)] }],
+        vis: Visibility::Inherited,
+        ident: Some(format_ident!("__phantom")),
+        colon_token: Some(Default::default()),
+        ty: parse_quote! { #core::marker::PhantomData<(#(#phantom,)*)> },
+    });
+
+    let fields = Fields::Named(FieldsNamed {
+        brace_token: Default::default(),
+        named: fields,
+    });
+    let mut builder_fields: Vec<_> = errors
+        .check(builder_fields(&fields, options))
+        .unwrap_or_default();
+    for f in &mut builder_fields {
+        if f.ident == "__receiver" {
+            f.receiver = true;
+            f.env = None;
+        }
+        // Building a sub-builder can fail, which `call()` cannot report.
+        if f.sub_builder.is_some() {
+            errors.push(syn::Error::new_spanned(
+                f.ty,
+                "`sub_builder` is not supported on function arguments",
+            ));
+        }
+    }
+    errors.finish()?;
+    let (skipped, builder_fields): (Vec<_>, Vec<_>) =
+        builder_fields.into_iter().partition(|f| f.skip);
+
+    // Methods are called through their type, with the receiver first.
+    let fn_ident = &sig.ident;
+    let func = match self_ty {
+        Some(self_ty) => quote! { <#self_ty>::#fn_ident },
+        None => quote! { #fn_ident },
+    };
+    let turbofish_args = sig
+        .generics
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            GenericParam::Type(tp) => Some(&tp.ident),
+            GenericParam::Const(cp) => Some(&cp.ident),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let turbofish = (!turbofish_args.is_empty()).then(|| quote! { ::<#(#turbofish_args),*> });
+    let call_args = fields
+        .iter()
+        .filter_map(|f| f.ident.as_ref())
+        .filter(|ident| *ident != "__phantom");
+    let asyncness = &sig.asyncness;
+    let unsafety = &sig.unsafety;
+    let awaited = asyncness.map(|_| quote! { .await });
+
+    let self_name = match self_ty {
+        Some(Type::Path(TypePath { path, .. })) => path
+            .segments
+            .last()
+            .map_or_else(String::new, |last| unraw(&last.ident)),
+        _ => String::new(),
+    };
+    let builder_ident = options.name.clone().unwrap_or_else(|| {
+        format_ident!("{}{}Builder", self_name, to_camel_case(&unraw(fn_ident)))
+    });
+    let args_ident = format_ident!("{}Args", unraw(&builder_ident));
+    let entry_fn = options
+        .constructor
+        .clone()
+        .unwrap_or_else(|| format_ident!("{}_builder", unraw(fn_ident)));
+    let error_ident = format_ident!("{}Error", unraw(&builder_ident));
+
+    let ctx = BuilderContext {
+        st_ident: &args_ident,
+        ctor: quote! { #args_ident },
+        entry_fn: None,
+        builder_ident: builder_ident.clone(),
+        doc_target: match self_name.is_empty() {
+            true => format!("the arguments of [`{}`]", unraw(fn_ident)),
+            false => format!("the arguments of [`{}::{}`]", self_name, unraw(fn_ident)),
+        },
+        vis: options.vis.clone().unwrap_or_else(|| vis.clone()),
+        build_fn: options
+            .build_fn
+            .clone()
+            .unwrap_or_else(|| Ident::new("call", Span::call_site())),
+        error_ty: quote! { #error_ident },
+        error_ident,
+        generics: &builder_generics,
+        fields: builder_fields,
+        skipped,
+        struct_default: false,
+        pattern: Pattern::Owned,
+        validate: None,
+        derives: &options.derives,
+        struct_attrs: &options.struct_attrs,
+        round_trip: false,
+        no_std: options.no_std.is_some(),
+        deserialize: false,
+        call: Some(FnCall {
+            qualifiers: quote! { #asyncness #unsafety },
+            output,
+            body: quote! { #func #turbofish(#(__args.#call_args),*) #awaited },
+        }),
+    };
+    ctx.check_method_names(false)?;
+    let builder_vis = &ctx.vis;
+    let builder_args = generic_args(&builder_generics);
+    let builder_where = &builder_generics.where_clause;
+    let args_fields = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let ty = &f.ty;
+        quote! { #ident: #ty }
+    });
+
+    // A method's builder starts out holding its receiver, and so in a state
+    // where `__receiver` is set.
+    let entry_params = impl_params(&fn_generics);
+    let entry_where = &fn_generics.where_clause;
+    let entry_doc = format!("Returns a new [`{}`] with no arguments set.", builder_ident);
+    let entry = match receiver {
+        Some(receiver_ty) => {
+            let state_mod = typestate::state_mod(&builder_ident);
+            let defaults = ctx.storage_defaults().into_iter().skip(1);
+            quote! {
+                #[doc = #entry_doc]
+                #builder_vis fn #entry_fn<#(#entry_params),*>(
+                    self: #receiver_ty,
+                ) -> #builder_ident<#(#builder_args,)* #state_mod::Set> #entry_where {
+                    #builder_ident {
+                        __receiver: #core::option::Option::Some(self),
+                        #(#defaults,)*
+                        __phantom: #core::marker::PhantomData,
+                    }
+                }
+            }
+        }
+        None => quote! {
+            #[doc = #entry_doc]
+            #builder_vis fn #entry_fn<#(#entry_params),*>()
+                -> #builder_ident<#(#builder_args),*> #entry_where
+            {
+                #core::default::Default::default()
+            }
+        },
+    };
+
+    let builder = typestate::expand(&ctx);
+    Ok((
+        entry,
+        quote! {
+            #[doc(hidden)]
+            #builder_vis struct #args_ident #builder_generics #builder_where {
+                #(#args_fields,)*
+            }
+
+            #builder
+        },
+    ))
+}
+
+/// Combines generics, lifetimes first as they must be.
+fn sort_generics(parts: &[&Generics]) -> Generics {
+    let params = parts.iter().flat_map(|g| &g.params);
+    let (lifetimes, others): (Vec<_>, Vec<_>) =
+        params.partition(|p| matches!(p, GenericParam::Lifetime(_)));
+    let predicates = parts
+        .iter()
+        .filter_map(|g| g.where_clause.as_ref())
+        .flat_map(|w| w.predicates.iter())
+        .collect::<Vec<_>>();
+    let where_clause: Option<WhereClause> =
+        (!predicates.is_empty()).then(|| parse_quote! { where #(#predicates),* });
+    Generics {
+        lt_token: Some(Default::default()),
+        params: lifetimes.into_iter().chain(others).cloned().collect(),
+        gt_token: Some(Default::default()),
+        where_clause,
+    }
+}
+
+/// What `ArgTypes` does with elided lifetimes.
+enum Elided {
+    /// Each one gets a new named lifetime.
+    Fresh,
+    /// They all become this lifetime.
+    As(Lifetime),
+    Keep,
+}
+
+/// Rewrites the types of a function's signature so that they mean the same
+/// in its builder, which is defined outside of the function and of its impl
+/// block: `Self` becomes the impl's type, and elided lifetimes named ones.
+struct ArgTypes<'a> {
+    self_ty: Option<&'a Type>,
+    elided: Elided,
+    /// Lifetimes introduced in place of elided ones.
+    fresh: Vec<Lifetime>,
+    /// Every lifetime mentioned by the rewritten types, other than `'static`.
+    seen: Vec<Lifetime>,
+    /// Whether an `impl Trait` type was found.
+    impl_trait: bool,
+}
+
+impl<'a> ArgTypes<'a> {
+    fn new(self_ty: Option<&'a Type>, elided: Elided) -> Self {
+        ArgTypes {
+            self_ty,
+            elided,
+            fresh: Vec::new(),
+            seen: Vec::new(),
+            impl_trait: false,
+        }
+    }
+
+    fn fresh(&mut self) -> Lifetime {
+        let lifetime = Lifetime::new(&format!("'__{}", self.fresh.len()), Span::call_site());
+        self.fresh.push(lifetime.clone());
+        lifetime
+    }
+
+    fn elided(&mut self) -> Option<Lifetime> {
+        match &self.elided {
+            Elided::Fresh => Some(self.fresh()),
+            Elided::As(lifetime) => Some(lifetime.clone()),
+            Elided::Keep => None,
+        }
+    }
+}
+
+impl VisitMut for ArgTypes<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let (Some(self_ty), Type::Path(TypePath { qself: None, path })) = (self.self_ty, &*ty) {
+            if path.leading_colon.is_none() && path.segments[0].ident == "Self" {
+                let rest = path.segments.iter().skip(1).collect::<Vec<_>>();
+                *ty = match rest.is_empty() {
+                    true => self_ty.clone(),
+                    false => parse_quote! { <#self_ty>::#(#rest)::* },
+                };
+            }
+        }
+        match ty {
+            Type::ImplTrait(_) => self.impl_trait = true,
+            // Elided lifetimes in function pointers are the pointer's own.
+            Type::BareFn(_) => return,
+            _ => {}
+        }
+        visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_type_reference_mut(&mut self, r: &mut TypeReference) {
+        if r.lifetime.is_none() {
+            r.lifetime = self.elided();
+        }
+        visit_mut::visit_type_reference_mut(self, r);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            if let Some(named) = self.elided() {
+                *lifetime = named;
+            }
+        }
+        if lifetime.ident != "static" && lifetime.ident != "_" && !self.seen.contains(lifetime) {
+            self.seen.push(lifetime.clone());
+        }
+    }
+
+    // Those of `Fn(&str)` too.
+    fn visit_parenthesized_generic_arguments_mut(&mut self, _: &mut ParenthesizedGenericArguments) {
+    }
+}
@@ -0,0 +1,60 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ConstParam, GenericParam, Generics, LifetimeDef, TypeParam};
+
+/// Generic arguments naming each of the struct's own parameters, for use when
+/// referring to the struct or its builder from inside an impl.
+pub(crate) fn generic_args(generics: &Generics) -> Vec<TokenStream> {
+    generics
+        .params
+        .iter()
+        .map(|p| match p {
+            GenericParam::Lifetime(LifetimeDef { lifetime, .. }) => quote! { #lifetime },
+            GenericParam::Type(TypeParam { ident, .. }) => quote! { #ident },
+            GenericParam::Const(ConstParam { ident, .. }) => quote! { #ident },
+        })
+        .collect()
+}
+
+/// The struct's generic parameters with their defaults removed, since those
+/// are only allowed on the type definition and not on impl headers.
+pub(crate) fn impl_params(generics: &Generics) -> Vec<GenericParam> {
+    generics
+        .params
+        .iter()
+        .cloned()
+        .map(|mut p| {
+            match &mut p {
+                GenericParam::Type(tp) => {
+                    tp.eq_token = None;
+                    tp.default = None;
+                }
+                GenericParam::Const(cp) => {
+                    cp.eq_token = None;
+                    cp.default = None;
+                }
+                GenericParam::Lifetime(_) => {}
+            }
+            p
+        })
+        .collect()
+}
+
+/// Marker for every lifetime and type parameter of the struct, so that the
+/// builder remains well formed even when a parameter is not mentioned by any
+/// of its fields.
+pub(crate) fn phantom_params(generics: &Generics, core: &TokenStream) -> Vec<TokenStream> {
+    generics
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            GenericParam::Lifetime(LifetimeDef { lifetime, .. }) => {
+                Some(quote! { #core::marker::PhantomData<&#lifetime ()> })
+            }
+            GenericParam::Type(TypeParam { ident, .. }) => {
+                Some(quote! { #core::marker::PhantomData<#ident> })
+            }
+            GenericParam::Const(_) => None,
+        })
+        .collect()
+}
@@ -1,257 +1,212 @@
+mod attrs;
+mod errors;
+mod expand;
+mod field;
+mod function;
+mod generics;
+mod naming;
+mod typestate;
+
+use crate::attrs::{struct_options, Pattern, StructOptions};
+use crate::errors::Errors;
+use crate::expand::BuilderContext;
+use crate::field::BuilderField;
+use crate::naming::{to_snake_case, unraw};
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::quote;
 use syn::{
-    parse_macro_input, spanned::Spanned, AngleBracketedGenericArguments, Attribute, Data,
-    DataStruct, DeriveInput, Field, GenericArgument, Ident, Lit, Meta, MetaList, MetaNameValue,
-    NestedMeta, Path, PathArguments, Type, TypePath,
+    parse_macro_input, parse_quote, Data, DataEnum, DataStruct, DataUnion, DeriveInput, Fields,
+    Ident, Item,
 };
 
-enum FieldWrapperType<'a> {
-    None,
-    Option(&'a Type),
-    Vec {
-        inner_ty: &'a Type,
-        each: Option<Result<String, syn::Error>>,
-    },
-}
-
-fn compare_path_with_str(p: &Path, s: &str) -> bool {
-    let parts = p
-        .segments
-        .iter()
-        .map(|seg| seg.ident.to_string())
-        .collect::<Vec<_>>();
-    parts.join("::") == s
-}
-
-fn find_builder_attrs(f: &Field) -> Vec<&Attribute> {
-    f.attrs
-        .iter()
-        .filter(|a| {
-            let segments = &a.path.segments;
-            match segments.len() {
-                1 => {
-                    let ai = &segments[0].ident;
-                    &ai.to_string() == "builder"
-                }
-                _ => false,
-            }
-        })
-        .collect::<Vec<_>>()
-}
-
-fn each_from_attribute(a: &Attribute) -> Result<String, syn::Error> {
-    let meta = a.parse_meta()?;
-    let span = meta
-        .span()
-        .join(a.tokens.span())
-        .unwrap_or_else(|| meta.span());
-
-    let nested = match &meta {
-        Meta::List(MetaList { path, nested, .. })
-            if compare_path_with_str(&path, "builder") && nested.len() == 1 =>
-        {
-            nested
-        }
-        _ => return Err(syn::Error::new(span, "expected `builder(each = \"...\")`")),
-    };
-
-    match &nested[0] {
-        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
-            path: nested_path,
-            lit: Lit::Str(lit),
-            ..
-        })) if compare_path_with_str(&nested_path, "each") => Ok(lit.value()),
-        _ => return Err(syn::Error::new(span, "expected `builder(each = \"...\")`")),
-    }
-}
-
 #[proc_macro_derive(Builder, attributes(builder))]
 pub fn derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
-    let st_ident = input.ident;
-    let builder_ident = Ident::new(&format!("{}Builder", st_ident), Span::call_site());
-
-    let st_fields = match input.data {
-        Data::Struct(DataStruct { ref fields, .. }) => fields,
-        _ => panic!("Builder is only support for structs"),
-    }
-    .into_iter()
-    .map(|f| {
-        // Process attributes
-        let attrs = find_builder_attrs(f);
-        let each = attrs.first().map(|a| each_from_attribute(*a));
-
-        // Process wrapper types
-        let segments = match f.ty {
-            Type::Path(TypePath {
-                qself: None,
-                path: Path { ref segments, .. },
-            }) => segments,
-            _ => unimplemented!(),
-        };
-        let seg = &segments[0];
-        let segi = &seg.ident;
-        let ty_name = segi.to_string();
-        let wrapper_ty = match &ty_name[..] {
-            "Option" | "Vec" => {
-                // I really wish we had some type info before macro expansion
-                let inner_ty = match seg.arguments {
-                    PathArguments::AngleBracketed(AngleBracketedGenericArguments {
-                        ref args,
-                        ..
-                    }) => match &args[0] {
-                        GenericArgument::Type(ty) => ty,
-                        _ => unimplemented!(),
-                    },
-                    _ => unimplemented!(),
-                };
-
-                if &ty_name[..] == "Option" {
-                    FieldWrapperType::Option(inner_ty)
-                } else {
-                    FieldWrapperType::Vec { inner_ty, each }
-                }
-            }
-            _ => FieldWrapperType::None,
-        };
-
-        (f, wrapper_ty)
-    })
-    .collect::<Vec<_>>();
+    expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
 
-    let fields = st_fields
-        .iter()
-        .map(|(f, wrapper_ty)| {
-            let fi = f.ident.clone().unwrap();
-            let fty = &f.ty;
+/// Generates a builder for the arguments of a function, or of the methods
+/// marked `#[builder]` in an impl block, such as
+/// `connect_builder().host(..).port(..).call()` for `fn connect`.
+#[proc_macro_attribute]
+pub fn builder(args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as Item);
 
-            match wrapper_ty {
-                FieldWrapperType::Option(_) => quote! { #fi: #fty},
-                _ => quote! { #fi: std::option::Option<#fty> },
-            }
-        })
-        .collect::<Vec<_>>();
+    function::expand(args.into(), item)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
 
-    let field_defaults = st_fields
-        .iter()
-        .map(|(f, wrapper_ty)| {
-            let fi = f.ident.clone().unwrap();
-            match wrapper_ty {
-                FieldWrapperType::Vec { each: Some(_), .. } => quote! {
-                    #fi: std::option::Option::Some(vec![])
-                },
-                _ => quote! {
-                    #fi: std::option::Option::None
-                },
+fn expand(input: &DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    // Problems with the struct's options do not stop its fields from being
+    // checked, so that everything wrong is reported at once.
+    let mut errors = Errors::default();
+    let options = errors
+        .check(struct_options(&input.attrs))
+        .unwrap_or_default();
+    let st_ident = &input.ident;
+
+    // Structs get a single `Foo::builder()`, enums one builder per variant
+    // such as `Shape::circle_builder()` returning a `ShapeCircleBuilder`.
+    let targets = match &input.data {
+        Data::Struct(DataStruct { fields, .. }) => vec![(
+            quote! { #st_ident },
+            options
+                .constructor
+                .clone()
+                .unwrap_or_else(|| Ident::new("builder", Span::call_site())),
+            options.name.clone().unwrap_or_else(|| {
+                Ident::new(&format!("{}Builder", unraw(st_ident)), Span::call_site())
+            }),
+            fields,
+            true,
+        )],
+        Data::Enum(DataEnum {
+            enum_token,
+            variants,
+            ..
+        }) => {
+            if options.default {
+                errors.push(syn::Error::new_spanned(
+                    enum_token,
+                    "struct-level `#[builder(default)]` is not supported for enums, \
+                     put `#[builder(default)]` on the variant fields instead",
+                ));
             }
-        })
-        .collect::<Vec<_>>();
-
-    let field_copies = st_fields
-        .iter()
-        .map(|(f, wrapper_ty)| {
-            let fi = f.ident.clone().unwrap();
-            match wrapper_ty {
-                FieldWrapperType::Option(_) => {
-                    quote! { #fi: self.#fi.clone() }
-                }
-                _ => quote! { #fi: self.#fi.clone().ok_or("No value for field")? },
+            // Every variant has its own builder, named after the variant.
+            for ident in options.name.iter().chain(&options.constructor) {
+                errors.push(syn::Error::new_spanned(
+                    ident,
+                    "`name` and `constructor` are not supported for enums, \
+                     whose builders are named after each variant",
+                ));
             }
-        })
-        .collect::<Vec<_>>();
+            variants
+                .iter()
+                .map(|v| {
+                    let vi = &v.ident;
+                    (
+                        quote! { #st_ident::#vi },
+                        Ident::new(
+                            &format!("{}_builder", to_snake_case(&unraw(vi))),
+                            Span::call_site(),
+                        ),
+                        Ident::new(
+                            &format!("{}{}Builder", unraw(st_ident), unraw(vi)),
+                            Span::call_site(),
+                        ),
+                        &v.fields,
+                        false,
+                    )
+                })
+                .collect()
+        }
+        Data::Union(DataUnion { union_token, .. }) => {
+            errors.push(syn::Error::new_spanned(
+                union_token,
+                "Builder is not supported for unions",
+            ));
+            Vec::new()
+        }
+    };
 
-    let methods = st_fields
-        .iter()
-        .filter(|(f, wrapper_ty)| {
-            if let Some(f_name) = &f.ident {
-                match wrapper_ty {
-                    FieldWrapperType::Vec {
-                        each: std::option::Option::Some(Ok(each)),
-                        ..
-                    } if each == &f_name.to_string() => false,
-                    _ => true,
-                }
-            } else {
-                false
-            }
-        })
-        .map(|(f, wrapper_ty)| {
-            let fi = f.ident.clone().unwrap();
-            let fty = &f.ty;
+    let mut output = proc_macro2::TokenStream::new();
+    for (ctor, entry_fn, builder_ident, fields, round_trip) in targets {
+        let error_ident = Ident::new(
+            &format!("{}Error", unraw(&builder_ident)),
+            Span::call_site(),
+        );
+        let error_ty = match &options.error {
+            Some(ty) => quote! { #ty },
+            None => quote! { #error_ident },
+        };
 
-            let inner_ty = match wrapper_ty {
-                FieldWrapperType::Option(ty) => ty,
-                _ => fty,
+        let pattern = match options.typestate {
+            true => Pattern::Owned,
+            false => options.pattern.unwrap_or(Pattern::Mutable),
+        };
+        let (skipped, fields): (Vec<_>, Vec<_>) =
+            match errors.check(builder_fields(fields, &options)) {
+                Some(fields) => fields.into_iter().partition(|f| f.skip),
+                None => continue,
             };
-            quote! {
-                pub fn #fi(&mut self, val: #inner_ty) -> &mut Self {
-                    self.#fi = std::option::Option::Some(val);
-                    self
-                }
-            }
-        })
-        .collect::<Vec<_>>();
-
-    let each_methods = st_fields
-        .iter()
-        .filter(|(_f, wrapper_ty)| match wrapper_ty {
-            FieldWrapperType::Vec { each: Some(_), .. } => true,
-            _ => false,
-        })
-        .map(|(f, wrapper_ty)| {
-            let fi = f.ident.clone().unwrap();
-            match wrapper_ty {
-                FieldWrapperType::Vec {
-                    inner_ty,
-                    each: Some(each),
-                } => match each {
-                    Ok(each) => {
-                        let each = Ident::new(each, fi.span());
-                        quote! {
-                            pub fn #each(&mut self, val: #inner_ty) -> &mut Self {
-                                if let ::std::option::Option::Some(v) = &mut self.#fi {
-                                    v.push(val);
-                                } else {
-                                    self.#fi = ::std::option::Option::Some(vec![val]);
-                                }
-                                self
-                            }
-                        }
-                    }
-                    Err(e) => e.to_compile_error(),
-                },
-                _ => unreachable!(),
-            }
-        })
-        .collect::<Vec<_>>();
-
-    let output = quote! {
-        impl #st_ident {
-            pub fn builder() -> #builder_ident {
-                #builder_ident {
-                    #(#field_defaults,)*
-                }
+        // Sub-builders are configured in place through `&mut self`, which an
+        // immutable builder never hands out.
+        if pattern == Pattern::Immutable {
+            for f in fields.iter().filter(|f| f.sub_builder.is_some()) {
+                errors.push(syn::Error::new_spanned(
+                    f.ty,
+                    "`sub_builder` fields need the mutable or owned builder pattern",
+                ));
             }
         }
 
-        pub struct #builder_ident {
-            #(#fields,)*
+        let doc_target = format!(
+            "[`{}`], created with [`{}::{}`]",
+            ctor.to_string().replace(' ', ""),
+            st_ident,
+            entry_fn
+        );
+        let ctx = BuilderContext {
+            st_ident,
+            ctor,
+            entry_fn: Some(entry_fn),
+            builder_ident,
+            doc_target,
+            vis: options.vis.clone().unwrap_or_else(|| parse_quote! { pub }),
+            build_fn: options
+                .build_fn
+                .clone()
+                .unwrap_or_else(|| Ident::new("build", Span::call_site())),
+            error_ident,
+            error_ty,
+            generics: &input.generics,
+            fields,
+            skipped,
+            struct_default: options.default,
+            pattern,
+            validate: options.validate.as_ref(),
+            derives: &options.derives,
+            struct_attrs: &options.struct_attrs,
+            round_trip,
+            no_std: options.no_std.is_some(),
+            deserialize: options.deserialize.is_some(),
+            call: None,
+        };
+
+        if errors
+            .check(ctx.check_method_names(!options.typestate))
+            .is_none()
+        {
+            continue;
         }
+        output.extend(if options.typestate {
+            typestate::expand(&ctx)
+        } else {
+            ctx.expand()
+        });
+    }
 
-        impl #builder_ident {
-            #(#methods)*
-            #(#each_methods)*
+    errors.finish()?;
+    Ok(output)
+}
 
-            pub fn build(&self) -> std::result::Result<#st_ident, std::boxed::Box<dyn std::error::Error>> {
-                Ok(#st_ident {
-                    #(#field_copies,)*
-                })
-            }
-        }
-    };
+/// Classifies every field, reporting problems with all of them at once.
+fn builder_fields<'a>(
+    fields: &'a Fields,
+    options: &StructOptions,
+) -> Result<Vec<BuilderField<'a>>, syn::Error> {
+    let mut errors = Errors::default();
+    let builder_fields = fields
+        .iter()
+        .enumerate()
+        .filter_map(|(i, f)| errors.check(BuilderField::new(f, i, options)))
+        .collect();
 
-    output.into()
+    errors.finish()?;
+    Ok(builder_fields)
 }
@@ -0,0 +1,209 @@
+use crate::expand::BuilderContext;
+use crate::field::BuilderField;
+use crate::generics::{generic_args, impl_params, phantom_params};
+use crate::naming::{to_snake_case, unraw};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::Ident;
+
+/// The hidden module holding the states of `builder_ident`'s fields.
+pub(crate) fn state_mod(builder_ident: &Ident) -> Ident {
+    Ident::new(
+        &format!("{}_state", to_snake_case(&builder_ident.to_string())),
+        Span::call_site(),
+    )
+}
+
+/// Generates a builder that tracks which required fields have been set in its
+/// type parameters, so that `build()` only type checks once all of them are.
+///
+/// Every required field gets a type parameter that is either `Unset` or `Set`
+/// from a hidden state module. Setters consume the builder and return it with
+/// that field's parameter switched to `Set`, and `build()` requires each
+/// parameter to implement the field's marker trait, which only `Set` does.
+pub(crate) fn expand(ctx: &BuilderContext) -> TokenStream {
+    let builder_ident = &ctx.builder_ident;
+    let vis = &ctx.vis;
+    let core = ctx.core();
+    let generics = ctx.generics;
+    let state_mod = state_mod(builder_ident);
+
+    // One entry per required field: the field and the name of its type
+    // parameter. Parameters are numbered rather than named after their fields,
+    // whose names could turn into the same parameter or one of the struct's.
+    let required = ctx
+        .fields
+        .iter()
+        .filter(|f| ctx.is_required(f))
+        .enumerate()
+        .map(|(i, f)| (f, format_ident!("__S{}", i, span = f.ident.span())))
+        .collect::<Vec<_>>();
+    let params = required.iter().map(|(_, p)| p).collect::<Vec<_>>();
+    let st_params = generics.params.iter().collect::<Vec<_>>();
+    let st_impl_params = impl_params(generics);
+    let st_args = generic_args(generics);
+    let st_phantom = phantom_params(generics, &core);
+    let where_clause = &generics.where_clause;
+
+    let storage = ctx.storage();
+    let builder_attrs = ctx.builder_attrs();
+    // The builder is consumed by `build()`, so fields are moved out rather
+    // than cloned.
+    let build_body = ctx.build_body(|fi| quote! { self.#fi });
+    let mut build_bounds = required
+        .iter()
+        .map(|(f, param)| {
+            let fi = &f.ident;
+            quote! { #param: #state_mod::#fi }
+        })
+        .collect::<Vec<_>>();
+    build_bounds.extend(ctx.build_bounds());
+    let build_fns = ctx.build_fns(quote! { self }, &build_bounds, build_body);
+
+    let markers = required.iter().map(|(f, _)| {
+        let fi = &f.ident;
+        let message = format!(
+            "required field `{}` is not set on `{}`",
+            unraw(fi),
+            builder_ident
+        );
+        let label = format!(
+            "call `.{}(...)` before `.{}()`",
+            unraw(&f.setter_ident),
+            ctx.build_fn
+        );
+        let cfgs = f.cfg_attrs();
+        quote! {
+            #cfgs
+            #[diagnostic::on_unimplemented(message = #message, label = #label)]
+            pub trait #fi {}
+            #cfgs
+            impl #fi for Set {}
+        }
+    });
+
+    let methods = ctx
+        .fields
+        .iter()
+        .filter(|f| !f.each_shadows_setter() && !f.receiver)
+        .map(|f| {
+            if let Some(accessor) = ctx.sub_builder_accessor(f) {
+                return accessor;
+            }
+            let fi = &f.ident;
+            let setter_fi = &f.setter_ident;
+            let setter_ty = f.setter_ty();
+
+            let pos = match required.iter().position(|(r, _)| r.ident == *fi) {
+                Some(pos) => pos,
+                // Optional fields do not change the builder's state, so they
+                // get the same setters as any owned builder.
+                None => return ctx.field_setter(f),
+            };
+
+            let next_params = params.iter().enumerate().map(|(i, p)| {
+                if i == pos {
+                    quote! { #state_mod::Set }
+                } else {
+                    quote! { #p }
+                }
+            });
+            let ret = quote! { #builder_ident<#(#st_args,)* #(#next_params),*> };
+            let (moves, move_cfgs): (Vec<_>, Vec<_>) = ctx
+                .fields
+                .iter()
+                .filter(|other| other.ident != *fi)
+                .map(|other| (&other.ident, other.cfg_attrs()))
+                .unzip();
+            let try_setter = f.setter.try_into.then(|| {
+                let try_fi = BuilderField::try_setter_ident(setter_fi);
+                let attrs = f.try_setter_attrs(setter_fi);
+                quote! {
+                    #attrs
+                    #vis fn #try_fi<__V: #core::convert::TryInto<#setter_ty>>(
+                        self,
+                        val: __V,
+                    ) -> #core::result::Result<#ret, <__V as #core::convert::TryInto<#setter_ty>>::Error> {
+                        let val: #setter_ty = #core::convert::TryInto::try_into(val)?;
+                        #core::result::Result::Ok(self.#setter_fi(val))
+                    }
+                }
+            });
+
+            let (params, value, error) = ctx.field_setter_input(f);
+            let built = quote! {
+                #builder_ident {
+                    #fi: #core::option::Option::Some(#value),
+                    #(#move_cfgs #moves: self.#moves,)*
+                    __phantom: #core::marker::PhantomData,
+                }
+            };
+            let (ret, built) = match error {
+                Some(error) => (
+                    quote! { #core::result::Result<#ret, #error> },
+                    quote! { #core::result::Result::Ok(#built) },
+                ),
+                None => (ret, built),
+            };
+
+            let attrs = f.setter_attrs(f.setter_docs(&format!("Sets `{}`.", unraw(fi))));
+            quote! {
+                #attrs
+                #vis fn #setter_fi(self, #params) -> #ret {
+                    #built
+                }
+
+                #try_setter
+            }
+        });
+
+    let each_methods = ctx.fields.iter().filter_map(|f| ctx.each_methods(f));
+    let introspection_fns = ctx.introspection_fns(false);
+    let merge_fns = ctx.merge_fns();
+    let (fill_from_env, from_env_impl) = ctx.env_fns();
+
+    let set_params = params.iter().map(|_| quote! { #state_mod::Set });
+    let round_trip = ctx.round_trip(&quote! { #builder_ident<#(#st_args,)* #(#set_params),*> });
+    let default_impl = ctx.default_impl();
+    let error_type = ctx.error_type();
+    let sub_builder_trait = ctx.sub_builder_trait();
+    let entry_impl = ctx.entry_impl();
+
+    quote! {
+        #entry_impl
+
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #vis mod #state_mod {
+            // Derived so that derives requested for the builder apply whatever
+            // its state.
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct Set;
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct Unset;
+
+            #(#markers)*
+        }
+
+        #builder_attrs
+        #vis struct #builder_ident<#(#st_params,)* #(#params = #state_mod::Unset),*> #where_clause {
+            #(#storage,)*
+            __phantom: #core::marker::PhantomData<(#(#st_phantom,)* #(#params,)*)>,
+        }
+
+        impl<#(#st_impl_params,)* #(#params),*> #builder_ident<#(#st_args,)* #(#params),*> #where_clause {
+            #(#methods)*
+            #(#each_methods)*
+            #introspection_fns
+            #merge_fns
+            #fill_from_env
+            #build_fns
+        }
+
+        #from_env_impl
+        #default_impl
+        #round_trip
+        #error_type
+        #sub_builder_trait
+    }
+}
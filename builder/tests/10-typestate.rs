@@ -0,0 +1,35 @@
+// With #[builder(typestate)] on the struct, the builder remembers in its type
+// which required fields have been set. Setters consume the builder and return
+// it in its new state, and `build()` only exists once every required field
+// has been given a value.
+//
+// Optional and `each` fields do not take part in the state and can be set in
+// any order, any number of times.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .arg("build".to_owned())
+        .env(vec![])
+        .current_dir("..".to_owned())
+        .executable("cargo".to_owned())
+        .arg("--release".to_owned())
+        .build()
+        .unwrap();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert!(command.env.is_empty());
+    assert_eq!(command.current_dir.unwrap(), "..");
+}
@@ -0,0 +1,19 @@
+// Calling `build()` on a typestate builder before all required fields are set
+// is a compile error naming the field that is still missing.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let _ = Command::builder()
+        .executable("cargo".to_owned())
+        .current_dir("..".to_owned())
+        .build();
+}
@@ -0,0 +1,34 @@
+// The builder reports failures through a dedicated error type named after the
+// builder. When required fields are missing, `build()` returns
+// `CommandBuilderError::MissingFields` listing every one of them, not just the
+// first, in the order they are declared.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    args: Vec<String>,
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    builder.args(vec![]);
+
+    let err = builder.build().err().unwrap();
+    assert_eq!(
+        err,
+        CommandBuilderError::MissingFields(vec!["executable".to_owned(), "env".to_owned()]),
+    );
+    assert_eq!(err.to_string(), "missing fields: executable, env");
+
+    builder.executable("cargo".to_owned());
+    let err = builder.build().err().unwrap();
+    assert_eq!(err.to_string(), "missing field: env");
+
+    // Usable with `?` in functions returning a boxed error.
+    let boxed: Box<dyn std::error::Error> = Box::new(err);
+    assert_eq!(boxed.to_string(), "missing field: env");
+}
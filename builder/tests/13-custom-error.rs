@@ -0,0 +1,39 @@
+// A struct can name its own error type with #[builder(error = "...")]. The
+// generated `build()` then returns that type, converting the builder's error
+// into it through a `From` impl provided by the caller.
+
+use derive_builder::Builder;
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Incomplete(String),
+}
+
+impl From<ServerConfigBuilderError> for ConfigError {
+    fn from(err: ServerConfigBuilderError) -> Self {
+        ConfigError::Incomplete(err.to_string())
+    }
+}
+
+#[derive(Builder)]
+#[builder(error = "ConfigError")]
+pub struct ServerConfig {
+    host: String,
+    port: u16,
+}
+
+fn main() {
+    let result: Result<ServerConfig, ConfigError> = ServerConfig::builder().build();
+    assert_eq!(
+        result.err(),
+        Some(ConfigError::Incomplete("missing fields: host, port".to_owned())),
+    );
+
+    let config = ServerConfig::builder()
+        .host("localhost".to_owned())
+        .port(8080)
+        .build()
+        .unwrap();
+    assert_eq!(config.host, "localhost");
+    assert_eq!(config.port, 8080);
+}
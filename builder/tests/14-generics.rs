@@ -0,0 +1,66 @@
+// Generic structs get a builder with the same generic parameters. Lifetimes,
+// type parameters with their bounds, const generics, defaults and the where
+// clause are all carried over to the builder and its impls.
+
+use derive_builder::Builder;
+use std::fmt::Debug;
+
+pub trait Codec {
+    fn name(&self) -> &'static str;
+}
+
+#[derive(Clone)]
+pub struct Json;
+
+impl Codec for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+}
+
+#[derive(Builder)]
+pub struct Request<'a, T: Codec, const N: usize, U = u8>
+where
+    U: Debug,
+{
+    body: &'a [u8],
+    codec: T,
+    headers: [U; N],
+    #[builder(each = "tag")]
+    tags: Vec<&'a str>,
+    timeout: Option<U>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Response<'a, T: Codec> {
+    body: &'a [u8],
+    codec: T,
+}
+
+fn main() {
+    let body = b"{}".to_vec();
+    let request = Request::builder()
+        .body(&body)
+        .codec(Json)
+        .headers([1, 2])
+        .tag("a")
+        .tag("b")
+        .build()
+        .unwrap();
+
+    let request: Request<Json, 2> = request;
+    assert_eq!(request.body, b"{}");
+    assert_eq!(request.codec.name(), "json");
+    assert_eq!(request.headers, [1, 2]);
+    assert_eq!(request.tags, vec!["a", "b"]);
+    assert_eq!(request.timeout, None);
+
+    let response = Response::builder()
+        .codec(Json)
+        .body(&body)
+        .build()
+        .unwrap();
+    assert_eq!(response.body, b"{}");
+    assert_eq!(response.codec.name(), "json");
+}
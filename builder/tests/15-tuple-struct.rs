@@ -0,0 +1,26 @@
+// Tuple structs get positional setters named `_0`, `_1`, and so on. A field
+// can be given a more descriptive setter with #[builder(name = "...")].
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Rgb(u8, u8, #[builder(name = "blue")] u8);
+
+#[derive(Builder)]
+pub struct Labels(#[builder(each = "label")] Vec<String>, Option<String>);
+
+fn main() {
+    let color = Rgb::builder()._0(255)._1(128).blue(0).build().unwrap();
+    assert_eq!((color.0, color.1, color.2), (255, 128, 0));
+
+    let err = Rgb::builder()._1(128).build().err().unwrap();
+    assert_eq!(err.to_string(), "missing fields: _0, blue");
+
+    let labels = Labels::builder()
+        .label("a".to_owned())
+        .label("b".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(labels.0, vec!["a", "b"]);
+    assert_eq!(labels.1, None);
+}
@@ -0,0 +1,47 @@
+// Deriving Builder on an enum generates one builder per variant, reached
+// through a snake case constructor named after the variant. Each variant
+// builder follows the same rules as a struct builder, including positional
+// setters for tuple variants.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub enum Shape {
+    Circle {
+        radius: f64,
+        label: Option<String>,
+    },
+    Polygon {
+        #[builder(each = "point")]
+        points: Vec<(f64, f64)>,
+    },
+    Square(#[builder(name = "side")] f64),
+}
+
+fn main() {
+    let circle = Shape::circle_builder().radius(1.5).build().unwrap();
+    assert_eq!(
+        circle,
+        Shape::Circle {
+            radius: 1.5,
+            label: None,
+        },
+    );
+
+    let triangle = Shape::polygon_builder()
+        .point((0.0, 0.0))
+        .point((1.0, 0.0))
+        .point((0.0, 1.0))
+        .build()
+        .unwrap();
+    assert_eq!(
+        triangle,
+        Shape::Polygon {
+            points: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        },
+    );
+
+    let square: ShapeSquareBuilder = Shape::square_builder();
+    let err: ShapeSquareBuilderError = square.build().err().unwrap();
+    assert_eq!(err.to_string(), "missing field: side");
+}
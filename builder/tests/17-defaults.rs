@@ -0,0 +1,84 @@
+// Fields marked #[builder(default)] fall back to their type's `Default` impl
+// when never set, and #[builder(default = "...")] to the given expression.
+// Plain literals can be written without quotes. Neither kind of field is
+// required any longer.
+//
+// With #[builder(default)] on the struct itself, every field that is not set
+// and has no default of its own is taken from the struct's `Default` impl.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct RateLimiter {
+    name: String,
+    #[builder(default)]
+    burst: u32,
+    #[builder(default = "std::time::Duration::from_secs(1)")]
+    window: std::time::Duration,
+    #[builder(default = 100)]
+    max: u64,
+    #[builder(default = "Some(\"limiter\".to_owned())")]
+    label: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(default)]
+pub struct Server {
+    host: String,
+    port: u16,
+    #[builder(default = "4")]
+    workers: usize,
+    #[builder(each = "alias")]
+    aliases: Vec<String>,
+    tls: Option<bool>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+            workers: 1,
+            aliases: vec!["local".to_owned()],
+            tls: Some(false),
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Client {
+    url: String,
+    #[builder(default = 3)]
+    retries: u8,
+}
+
+fn main() {
+    let limiter = RateLimiter::builder()
+        .name("api".to_owned())
+        .max(10)
+        .build()
+        .unwrap();
+    assert_eq!(limiter.burst, 0);
+    assert_eq!(limiter.window, std::time::Duration::from_secs(1));
+    assert_eq!(limiter.max, 10);
+    assert_eq!(limiter.label.as_deref(), Some("limiter"));
+
+    let err = RateLimiter::builder().build().err().unwrap();
+    assert_eq!(err.to_string(), "missing field: name");
+
+    let server = Server::builder().port(9000).build().unwrap();
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 9000);
+    assert_eq!(server.workers, 4);
+    assert_eq!(server.aliases, vec!["local"]);
+    assert_eq!(server.tls, Some(false));
+
+    let server = Server::builder().alias("a".to_owned()).tls(true).build().unwrap();
+    assert_eq!(server.aliases, vec!["a"]);
+    assert_eq!(server.tls, Some(true));
+
+    let client = Client::builder().url("http://x".to_owned()).build().unwrap();
+    assert_eq!(client.url, "http://x");
+    assert_eq!(client.retries, 3);
+}
@@ -0,0 +1,100 @@
+// #[builder(setter(into))] makes a setter accept anything convertible into
+// the field type, so call sites can pass `&str` for a `String` field.
+//
+// #[builder(setter(try_into))] adds a fallible `try_<name>` setter next to
+// the regular one. It accepts anything with a `TryInto` conversion to the
+// field type and returns the conversion error when it fails.
+//
+// Both apply to `each` setters too, and given on the struct they apply to
+// every field. A map's `each` setter takes a key and a value, which `into`
+// converts separately, so it has no `try_into` variant, and given on the
+// struct `try_into` leaves such fields out.
+
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+use std::num::TryFromIntError;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(setter(into))]
+    executable: String,
+    #[builder(each = "arg", setter(into))]
+    args: Vec<String>,
+    #[builder(setter(into))]
+    current_dir: Option<String>,
+}
+
+#[derive(Builder)]
+pub struct Limits {
+    #[builder(setter(try_into))]
+    max_connections: u16,
+    #[builder(each = "port", setter(try_into))]
+    ports: Vec<u16>,
+}
+
+#[derive(Builder)]
+#[builder(setter(into))]
+pub struct Labels {
+    name: String,
+    description: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(typestate, setter(into, try_into))]
+pub struct Endpoint {
+    host: String,
+    port: u16,
+}
+
+#[derive(Builder)]
+#[builder(setter(into, try_into))]
+pub struct Request {
+    path: String,
+    #[builder(each = "header")]
+    headers: BTreeMap<String, String>,
+}
+
+fn main() -> Result<(), TryFromIntError> {
+    let command = Command::builder()
+        .executable("cargo")
+        .arg("build")
+        .arg(String::from("--release"))
+        .current_dir("..")
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+
+    let mut limits = Limits::builder();
+    limits.try_max_connections(1024u64)?.try_port(80i32)?;
+    assert!(limits.try_port(-1i32).is_err());
+    assert!(limits.try_max_connections(100_000u32).is_err());
+    limits.port(443);
+    let limits = limits.build().unwrap();
+    assert_eq!(limits.max_connections, 1024);
+    assert_eq!(limits.ports, vec![80, 443]);
+
+    let labels = Labels::builder().name("web").description("frontend").build().unwrap();
+    assert_eq!(labels.name, "web");
+    assert_eq!(labels.description.as_deref(), Some("frontend"));
+
+    let endpoint = Endpoint::builder()
+        .host("localhost")
+        .try_port(8080u64)?
+        .build()
+        .unwrap();
+    assert_eq!(endpoint.host, "localhost");
+    assert_eq!(endpoint.port, 8080);
+
+    let request = Request::builder()
+        .try_path("/")
+        .unwrap()
+        .header("accept", "text/html")
+        .build()
+        .unwrap();
+    assert_eq!(request.path, "/");
+    assert_eq!(request.headers["accept"], "text/html");
+
+    Ok(())
+}
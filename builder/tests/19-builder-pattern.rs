@@ -0,0 +1,76 @@
+// The shape of the generated builder is chosen with #[builder(pattern = "...")].
+//
+//   - "mutable", the default: setters take and return `&mut self`, and
+//     `build(&self)` clones every field out of the builder.
+//
+//   - "owned": setters and `build(self)` consume the builder, moving the fields
+//     into the built value. No field needs to implement `Clone`.
+//
+//   - "immutable": setters take `&self` and return an updated copy, so one
+//     partially configured builder can be the base for several others.
+
+use derive_builder::Builder;
+use std::sync::mpsc::{channel, Receiver};
+
+// Deliberately not Clone.
+pub struct Handle(i32);
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Payload {
+    handle: Handle,
+    rx: Receiver<u8>,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+    note: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "immutable")]
+pub struct Request {
+    method: String,
+    path: String,
+    #[builder(each = "header")]
+    headers: Vec<String>,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "mutable")]
+pub struct Counter {
+    start: u32,
+}
+
+fn main() {
+    let (tx, rx) = channel();
+    tx.send(7).unwrap();
+
+    let payload = Payload::builder()
+        .handle(Handle(3))
+        .rx(rx)
+        .tag("a".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(payload.handle.0, 3);
+    assert_eq!(payload.rx.recv().unwrap(), 7);
+    assert_eq!(payload.tags, vec!["a"]);
+    assert!(payload.note.is_none());
+
+    let base = Request::builder()
+        .method("GET".to_owned())
+        .header("Accept: */*".to_owned());
+    let index = base.path("/".to_owned()).build().unwrap();
+    let about = base
+        .path("/about".to_owned())
+        .header("X-Page: about".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(index.path, "/");
+    assert_eq!(index.headers, vec!["Accept: */*"]);
+    assert_eq!(about.path, "/about");
+    assert_eq!(about.headers, vec!["Accept: */*", "X-Page: about"]);
+    assert!(base.build().is_err());
+
+    let mut counter = Counter::builder();
+    counter.start(1);
+    assert_eq!(counter.build().unwrap().start, 1);
+}
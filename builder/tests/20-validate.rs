@@ -0,0 +1,59 @@
+// #[builder(validate = "path::to::fn")] runs a check on the fully assembled
+// value before `build()` returns it. The function takes a reference to the
+// built value and returns `Result<(), E>` for any displayable `E`. A rejection
+// comes back from `build()` as `<Builder>Error::Validation` with the message.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(validate = "RateLimiter::check")]
+pub struct RateLimiter {
+    min: u32,
+    #[builder(default = 100)]
+    max: u32,
+}
+
+impl RateLimiter {
+    fn check(&self) -> Result<(), String> {
+        if self.min > self.max {
+            return Err(format!("min ({}) > max ({})", self.min, self.max));
+        }
+        Ok(())
+    }
+}
+
+mod checks {
+    pub fn non_empty(name: &super::Name) -> Result<(), &'static str> {
+        if name.value.is_empty() {
+            Err("name must not be empty")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", validate = "checks::non_empty")]
+pub struct Name {
+    value: String,
+}
+
+fn main() {
+    let limiter = RateLimiter::builder().min(10).build().unwrap();
+    assert_eq!((limiter.min, limiter.max), (10, 100));
+
+    let err = RateLimiter::builder().min(10).max(5).build().unwrap_err();
+    assert_eq!(
+        err,
+        RateLimiterBuilderError::Validation("min (10) > max (5)".to_owned()),
+    );
+    assert_eq!(err.to_string(), "min (10) > max (5)");
+
+    // Missing fields are reported before validation runs.
+    let err = RateLimiter::builder().build().unwrap_err();
+    assert_eq!(err.to_string(), "missing field: min");
+
+    assert!(Name::builder().value("x".to_owned()).build().is_ok());
+    let err = Name::builder().value(String::new()).build().err().unwrap();
+    assert_eq!(err.to_string(), "name must not be empty");
+}
@@ -0,0 +1,59 @@
+// The `each` setters are not limited to Vec. Maps get a two-argument setter
+// taking the key and the value, sets and deques get a one-argument setter just
+// like Vec, and the collection type may be written with its full path.
+//
+// Any other type implementing Extend + Default can opt in with
+// #[builder(each(name = "...", collection))]; its setter accepts whatever item
+// type the collection can be extended with.
+//
+// Every field with an `each` setter also gets a bulk `extend_<field>` method
+// taking any iterator of items.
+
+use derive_builder::Builder;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Tags(Vec<String>);
+
+impl Extend<&'static str> for Tags {
+    fn extend<I: IntoIterator<Item = &'static str>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(str::to_owned));
+    }
+}
+
+#[derive(Builder, Debug)]
+pub struct Request {
+    #[builder(each = "header")]
+    headers: HashMap<String, String>,
+    #[builder(each = "flag")]
+    flags: std::collections::BTreeSet<u8>,
+    #[builder(each = "step")]
+    steps: VecDeque<String>,
+    #[builder(each(name = "tag", collection))]
+    tags: Tags,
+}
+
+fn main() {
+    let request = Request::builder()
+        .header("accept".to_owned(), "*/*".to_owned())
+        .header("host".to_owned(), "example.com".to_owned())
+        .flag(3)
+        .flag(1)
+        .flag(3)
+        .step("first".to_owned())
+        .extend_steps(vec!["second".to_owned(), "third".to_owned()])
+        .tag("a")
+        .extend_tags(["b", "c"])
+        .build()
+        .unwrap();
+
+    assert_eq!(request.headers.len(), 2);
+    assert_eq!(request.headers["host"], "example.com");
+    assert_eq!(request.flags, BTreeSet::from([1, 3]));
+    assert_eq!(request.steps, ["first", "second", "third"]);
+    assert_eq!(request.tags, Tags(vec!["a".into(), "b".into(), "c".into()]));
+
+    let empty = Request::builder().build().unwrap();
+    assert!(empty.headers.is_empty());
+    assert_eq!(empty.tags, Tags::default());
+}
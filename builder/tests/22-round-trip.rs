@@ -0,0 +1,76 @@
+// An existing value can be turned back into a builder to derive new values
+// from it. `impl From<Foo> for FooBuilder` consumes the value while
+// `foo.to_builder()` clones it, and either way every field starts out set,
+// `each` collections included, so only the fields that change need setting.
+//
+// `to_builder` needs every field to be Clone, but that is only checked where
+// it is called; structs with other fields still get the From impl.
+//
+// Typestate builders come out of both with every required field marked as
+// set, ready to build.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Config {
+    host: String,
+    port: u16,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    timeout: Option<u32>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Query {
+    table: String,
+    limit: Option<usize>,
+}
+
+pub struct Connection;
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Client {
+    name: String,
+    connection: Connection,
+}
+
+fn main() {
+    let base = Config::builder()
+        .host("localhost".to_owned())
+        .port(8080)
+        .arg("-v".to_owned())
+        .timeout(30)
+        .build()
+        .unwrap();
+
+    let derived = base.to_builder().port(9090).arg("-q".to_owned()).build().unwrap();
+    assert_eq!(derived.host, "localhost");
+    assert_eq!(derived.port, 9090);
+    assert_eq!(derived.args, vec!["-v", "-q"]);
+    assert_eq!(derived.timeout, Some(30));
+
+    let mut builder = ConfigBuilder::from(base);
+    builder.host("example.com".to_owned());
+    let moved = builder.build().unwrap();
+    assert_eq!(moved.host, "example.com");
+    assert_eq!(moved.port, 8080);
+    assert_eq!(moved.args, vec!["-v"]);
+
+    let query = Query::builder().table("users".to_owned()).build().unwrap();
+    let query = query.to_builder().limit(10).build().unwrap();
+    assert_eq!(query.table, "users");
+    assert_eq!(query.limit, Some(10));
+
+    let client = Client::builder()
+        .name("primary".to_owned())
+        .connection(Connection)
+        .build()
+        .unwrap();
+    let client = ClientBuilder::from(client)
+        .name("secondary".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(client.name, "secondary");
+}
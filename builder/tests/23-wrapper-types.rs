@@ -0,0 +1,89 @@
+// Option and the collections are recognized however they are spelled, with
+// their full path through `std`, `core` or `alloc` and with or without a
+// leading `::`. Fields of any other kind of type, such as references, tuples,
+// arrays, function pointers or qualified paths, are plain required fields.
+//
+// The macro only sees the tokens of a type, so an alias of Option looks like
+// any other type. Mark such fields with #[builder(optional)] to get the same
+// behavior as a field spelled `Option<T>`.
+
+extern crate alloc;
+
+use derive_builder::Builder;
+
+pub type Port = Option<u16>;
+
+pub trait Backend {
+    type Handle;
+}
+
+pub struct Memory;
+
+impl Backend for Memory {
+    type Handle = usize;
+}
+
+macro_rules! with_type {
+    ($ty:ty) => {
+        #[derive(Builder)]
+        pub struct Wrapped {
+            value: $ty,
+        }
+    };
+}
+
+with_type!(Option<String>);
+
+#[derive(Builder)]
+pub struct Everything<'a> {
+    full: std::option::Option<String>,
+    rooted: ::core::option::Option<u8>,
+    #[builder(each = "item")]
+    items: alloc::vec::Vec<u32>,
+    #[builder(each = "entry")]
+    entries: ::std::collections::btree_map::BTreeMap<String, bool>,
+    #[builder(optional)]
+    port: Port,
+    name: &'a str,
+    pair: (u8, char),
+    bytes: [u8; 4],
+    callback: fn(u32) -> u32,
+    handle: <Memory as Backend>::Handle,
+    pointer: *const u8,
+}
+
+fn main() {
+    let everything = Everything::builder()
+        .item(1)
+        .entry("on".to_owned(), true)
+        .port(8080)
+        .name("name")
+        .pair((1, 'a'))
+        .bytes([1, 2, 3, 4])
+        .callback(|x| x + 1)
+        .handle(7)
+        .pointer(std::ptr::null())
+        .build()
+        .unwrap();
+
+    assert_eq!(everything.full, None);
+    assert_eq!(everything.rooted, None);
+    assert_eq!(everything.items, vec![1]);
+    assert_eq!(everything.entries["on"], true);
+    assert_eq!(everything.port, Some(8080));
+    assert_eq!(everything.name, "name");
+    assert_eq!(everything.pair, (1, 'a'));
+    assert_eq!(everything.bytes, [1, 2, 3, 4]);
+    assert_eq!((everything.callback)(1), 2);
+    assert_eq!(everything.handle, 7);
+    assert!(everything.pointer.is_null());
+
+    let wrapped = Wrapped::builder().build().unwrap();
+    assert_eq!(wrapped.value, None);
+
+    let err = Everything::builder().build().err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "missing fields: name, pair, bytes, callback, handle, pointer",
+    );
+}
@@ -0,0 +1,65 @@
+// Every invalid builder attribute on a struct's fields is reported in the same
+// compile, each error pointing at the attribute or value at fault, instead of
+// the macro giving up at the first one.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(each = "not an ident")]
+    args: Vec<String>,
+    #[builder(each(collection))]
+    env: Vec<String>,
+    #[builder(each(name = "flag", sorted))]
+    flags: Vec<String>,
+    #[builder(setter(into, from))]
+    current_dir: Option<String>,
+    #[builder(default = "1 +")]
+    retries: u32,
+    #[builder]
+    timeout: u32,
+    #[builder(name = "3")]
+    user: String,
+    #[builder(sub_builder)]
+    servers: Vec<String>,
+    #[builder(merge = "append")]
+    hosts: Vec<String>,
+    #[builder(each = "port", merge = "prepend")]
+    ports: Vec<u16>,
+    #[builder(field_attr("doc"))]
+    shell: String,
+    #[builder(skip, each = "pid")]
+    pids: Vec<u32>,
+    #[builder(setter(with = "str::parse as fn(&str) -> Result<u8, std::num::ParseIntError>"))]
+    umask: u8,
+    #[builder(setter(with = |s| s))]
+    group: String,
+    #[builder(setter(fallible))]
+    nice: i8,
+    #[builder(each = "label", env = "LABELS")]
+    labels: Vec<String>,
+    #[builder(sub_builder, env = "LIMITS")]
+    limits: Limits,
+    #[builder(env = "USER_ID", setter(with = |id: &str| id.to_owned()))]
+    user_id: String,
+    #[builder(each = "var", setter(try_into))]
+    vars: std::collections::HashMap<String, String>,
+    #[builder(each(name = "mount", collection), setter(into))]
+    mounts: Mounts,
+}
+
+#[derive(Default)]
+pub struct Mounts(Vec<String>);
+
+impl Extend<String> for Mounts {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+#[derive(Builder, Clone, Default)]
+pub struct Limits {
+    files: u32,
+}
+
+fn main() {}
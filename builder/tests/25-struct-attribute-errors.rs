@@ -0,0 +1,57 @@
+// Invalid options on the struct itself are reported along with those on its
+// fields, as are builders requested for types they cannot support.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(pattern = "borrowed", validate = "not a path")]
+#[builder(typestate, error = "Box<", defaults)]
+pub struct Command {
+    #[builder(eac = "arg")]
+    args: Vec<String>,
+}
+
+#[derive(Builder)]
+#[builder(typestate, pattern = "immutable")]
+pub struct Query {
+    table: String,
+}
+
+#[derive(Builder)]
+#[builder(vis = "public", setter_prefix = "with-", derive("Clone"))]
+pub struct Server {
+    host: String,
+}
+
+#[derive(Builder)]
+#[builder(default, name = "ShapeBuilder")]
+pub enum Shape {
+    Circle { radius: f64 },
+}
+
+#[derive(Builder)]
+#[builder(no_std, env_prefix = "SENSOR_")]
+pub struct Sensor {
+    address: u8,
+}
+
+#[derive(Builder)]
+#[builder(no_std)]
+pub struct Probe {
+    #[builder(env = "PROBE_NAME")]
+    name: String,
+}
+
+#[derive(Builder)]
+#[builder(setter(into, fallible))]
+pub struct Timer {
+    interval: u64,
+}
+
+#[derive(Builder)]
+pub union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,93 @@
+// A field whose type derives Builder itself can be marked
+// #[builder(sub_builder)]. The builder then holds the field's builder instead
+// of a value, and the field's method returns `&mut` to it so that it can be
+// configured in place. The field's struct has to use the mutable pattern for
+// that: owned and typestate builders take themselves by value, and immutable
+// ones return a changed copy that would be thrown away.
+//
+// `build()` builds every sub-builder too, whether or not it was touched. The
+// missing fields of a sub-builder are reported along with the builder's own,
+// by their dotted path such as `server.port`, and its validation errors are
+// prefixed with the field name.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Clone, Debug, PartialEq)]
+pub struct Tls {
+    cert: String,
+    #[builder(default)]
+    verify: bool,
+}
+
+#[derive(Builder, Clone, Debug, PartialEq)]
+#[builder(validate = "Server::check")]
+pub struct Server {
+    host: String,
+    port: u16,
+    #[builder(sub_builder)]
+    tls: Tls,
+}
+
+impl Server {
+    fn check(&self) -> Result<(), &'static str> {
+        if self.port == 0 {
+            return Err("port must not be 0");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Service {
+    name: String,
+    #[builder(sub_builder)]
+    server: Server,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "owned")]
+pub struct Proxy {
+    #[builder(sub_builder)]
+    upstream: Server,
+}
+
+fn main() {
+    let mut builder = Service::builder();
+    builder.name("api".to_owned());
+    builder.server().host("localhost".to_owned()).port(8080);
+    builder.server().tls().cert("cert.pem".to_owned());
+    let service = builder.build().unwrap();
+    assert_eq!(service.server.port, 8080);
+    assert_eq!(service.server.tls.cert, "cert.pem");
+    assert!(!service.server.tls.verify);
+
+    let mut builder = Service::builder();
+    builder.server().host("localhost".to_owned());
+    let err = builder.build().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "missing fields: name, server.port, server.tls.cert",
+    );
+
+    builder.name("api".to_owned());
+    builder.server().port(0).tls().cert("cert.pem".to_owned());
+    let err = builder.build().unwrap_err();
+    assert_eq!(
+        err,
+        ServiceBuilderError::Validation("server: port must not be 0".to_owned()),
+    );
+
+    let mut builder = service.to_builder();
+    builder.server().port(9090);
+    assert_eq!(builder.build().unwrap().server.port, 9090);
+
+    let mut builder = Proxy::builder();
+    builder
+        .upstream()
+        .host("upstream".to_owned())
+        .port(80)
+        .tls()
+        .cert("cert.pem".to_owned());
+    let proxy = builder.build().unwrap();
+    assert_eq!(proxy.upstream.host, "upstream");
+}
@@ -0,0 +1,81 @@
+// Builders can be layered with `merge`, which takes another builder of the
+// same type and overrides every field that is set on it, leaving the others
+// alone. This is how configuration from defaults, a file and the command line
+// is combined, each source filling in its own builder.
+//
+// Collections with an `each` setter append the other builder's items to ours
+// by default. #[builder(merge = "replace")] makes them replaced like any other
+// field instead. Collections opted in with `each(name = "...", collection)`
+// are replaced unless marked #[builder(merge = "append")].
+//
+// Sub-builders are merged field by field as well.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Clone, Debug, PartialEq)]
+pub struct Log {
+    level: String,
+    #[builder(default)]
+    color: bool,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Config {
+    host: String,
+    port: u16,
+    timeout: Option<u32>,
+    #[builder(each = "include")]
+    includes: Vec<String>,
+    #[builder(each = "feature", merge = "replace")]
+    features: Vec<String>,
+    #[builder(sub_builder)]
+    log: Log,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "owned")]
+pub struct Flags {
+    #[builder(each = "flag")]
+    flags: Vec<char>,
+    verbose: Option<bool>,
+}
+
+fn main() {
+    let mut defaults = Config::builder();
+    defaults
+        .host("localhost".to_owned())
+        .port(80)
+        .include("base.toml".to_owned())
+        .feature("default".to_owned());
+    defaults.log().level("info".to_owned());
+
+    let mut file = Config::builder();
+    file.port(8080)
+        .timeout(30)
+        .include("site.toml".to_owned())
+        .feature("tls".to_owned());
+    file.log().color(true);
+
+    let mut cli = Config::builder();
+    cli.port(9090);
+    cli.log().level("debug".to_owned());
+
+    defaults.merge(file).merge(cli);
+    let config = defaults.build().unwrap();
+    assert_eq!(config.host, "localhost");
+    assert_eq!(config.port, 9090);
+    assert_eq!(config.timeout, Some(30));
+    assert_eq!(config.includes, ["base.toml", "site.toml"]);
+    assert_eq!(config.features, ["tls"]);
+    assert_eq!(config.log.level, "debug");
+    assert!(config.log.color);
+
+    let flags = Flags::builder()
+        .flag('a')
+        .verbose(true)
+        .merge(Flags::builder().flag('b'))
+        .build()
+        .unwrap();
+    assert_eq!(flags.flags, ['a', 'b']);
+    assert_eq!(flags.verbose, Some(true));
+}
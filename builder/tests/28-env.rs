@@ -0,0 +1,73 @@
+// Fields can be filled from environment variables. #[builder(env = "NAME")]
+// reads a field from the variable NAME, and #[builder(env_prefix = "APP_")]
+// on the struct reads every other field from the prefix followed by the
+// field's name in upper case, except collections with an `each` setter and
+// sub-builders.
+//
+// `fill_from_env()` parses each variable that is present with FromStr and sets
+// its field, leaving fields whose variable is absent as they were.
+// `from_env()` does the same on a new builder. A variable that fails to parse
+// is reported as `<Builder>Error::InvalidEnv`.
+
+use derive_builder::Builder;
+use std::env;
+
+#[derive(Builder, Debug)]
+#[builder(env_prefix = "ENV_TEST_")]
+pub struct Server {
+    host: String,
+    port: u16,
+    #[builder(env = "ENV_TEST_WORKER_COUNT")]
+    workers: Option<usize>,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+pub struct Retry {
+    #[builder(env = "ENV_TEST_RETRIES")]
+    attempts: u32,
+    #[builder(default = 10)]
+    delay_ms: u64,
+}
+
+fn main() {
+    env::set_var("ENV_TEST_HOST", "example.com");
+    env::set_var("ENV_TEST_PORT", "8080");
+    env::set_var("ENV_TEST_WORKER_COUNT", "4");
+
+    let server = ServerBuilder::from_env().unwrap().build().unwrap();
+    assert_eq!(server.host, "example.com");
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.workers, Some(4));
+    assert!(server.tags.is_empty());
+
+    // Variables override what was set before, other fields are kept.
+    env::remove_var("ENV_TEST_HOST");
+    let mut builder = Server::builder();
+    builder.host("localhost".to_owned()).port(80);
+    builder.fill_from_env().unwrap();
+    let server = builder.build().unwrap();
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 8080);
+
+    env::set_var("ENV_TEST_PORT", "eighty");
+    let err = Server::builder().fill_from_env().err().unwrap();
+    assert_eq!(
+        err,
+        ServerBuilderError::InvalidEnv {
+            var: "ENV_TEST_PORT".to_owned(),
+            message: "invalid digit found in string".to_owned(),
+        },
+    );
+    assert_eq!(
+        err.to_string(),
+        "invalid value in environment variable ENV_TEST_PORT: invalid digit found in string",
+    );
+
+    env::set_var("ENV_TEST_RETRIES", "3");
+    let retry = Retry::builder().fill_from_env().unwrap().build().unwrap();
+    assert_eq!(retry.attempts, 3);
+    assert_eq!(retry.delay_ms, 10);
+}
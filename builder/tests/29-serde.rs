@@ -0,0 +1,84 @@
+// With the `serde` feature, builders of structs marked
+// #[builder(deserialize)] implement serde::Deserialize, so that a
+// configuration file can be read into a builder even when it only has some of
+// the fields. Keys that are absent leave their field unset, to be set by hand
+// or filled by defaults before `build()` checks that nothing is missing.
+//
+// Collections take arrays just like the struct's own field would, and
+// sub-builders take nested objects, whose structs opt in too.
+//
+// Other builders are left as they are, so enabling the feature does not
+// change the code generated for crates that do not ask for it.
+//
+// Typestate builders cannot be deserialized, as which of their fields are set
+// is part of their type.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(deserialize)]
+pub struct Log {
+    level: String,
+    #[builder(default)]
+    color: bool,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(deserialize)]
+pub struct Config {
+    host: String,
+    port: u16,
+    #[builder(default = 30)]
+    timeout: u32,
+    user: Option<String>,
+    #[builder(each = "include")]
+    includes: Vec<String>,
+    #[builder(sub_builder)]
+    log: Log,
+}
+
+// Conflicts with the macro's own impl if it derived one.
+#[derive(Builder)]
+pub struct Plain {
+    name: String,
+}
+
+impl<'de> serde::Deserialize<'de> for PlainBuilder {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        let mut builder = Plain::builder();
+        builder.name(name);
+        Ok(builder)
+    }
+}
+
+fn main() {
+    let mut builder: ConfigBuilder = serde_json::from_str(
+        r#"{
+            "host": "example.com",
+            "includes": ["base.toml", "site.toml"],
+            "log": { "level": "debug" }
+        }"#,
+    )
+    .unwrap();
+
+    let err = builder.build().unwrap_err();
+    assert_eq!(err.to_string(), "missing field: port");
+
+    let config = builder.port(8080).include("local.toml".to_owned()).build().unwrap();
+    assert_eq!(config.host, "example.com");
+    assert_eq!(config.port, 8080);
+    assert_eq!(config.timeout, 30);
+    assert_eq!(config.user, None);
+    assert_eq!(config.includes, ["base.toml", "site.toml", "local.toml"]);
+    assert_eq!(config.log.level, "debug");
+    assert!(!config.log.color);
+
+    let err = serde_json::from_str::<ConfigBuilder>(r#"{ "port": "http" }"#)
+        .err()
+        .unwrap();
+    assert!(err.to_string().starts_with("invalid type: string \"http\", expected u16"));
+
+    let builder: PlainBuilder = serde_json::from_str(r#""plain""#).unwrap();
+    assert_eq!(builder.build().unwrap().name, "plain");
+}
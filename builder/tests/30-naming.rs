@@ -0,0 +1,69 @@
+// The names and visibility of what the derive generates can be changed on the
+// struct, to keep builders internal or to avoid clashing with methods the
+// struct already has:
+//
+//   - name = "..." renames the builder, and its error type along with it
+//   - vis = "..." sets the visibility of the builder, its methods, its error
+//     type and the method returning it, which are `pub` by default
+//   - setter_prefix = "..." is prepended to the name of every setter
+//   - constructor = "..." renames the method returning a new builder
+//   - build_fn = "..." renames `build()`
+
+mod config {
+    use derive_builder::Builder;
+
+    #[derive(Builder, Debug)]
+    #[builder(
+        name = "ConfigFactory",
+        vis = "pub(crate)",
+        setter_prefix = "with_",
+        constructor = "factory",
+        build_fn = "finish"
+    )]
+    pub struct Config {
+        host: String,
+        port: u16,
+        #[builder(each = "tag")]
+        tags: Vec<String>,
+    }
+
+    impl Config {
+        // Would clash with the generated methods without the options above.
+        pub fn builder() -> &'static str {
+            "not a builder"
+        }
+
+        pub fn host(&self) -> &str {
+            &self.host
+        }
+
+        pub fn port(&self) -> u16 {
+            self.port
+        }
+    }
+
+    #[derive(Builder)]
+    #[builder(typestate, setter_prefix = "set_", build_fn = "done")]
+    pub struct Query {
+        pub table: String,
+    }
+}
+
+use config::{Config, ConfigFactory, ConfigFactoryError, Query};
+
+fn main() {
+    let mut factory: ConfigFactory = Config::factory();
+    factory
+        .with_host("localhost".to_owned())
+        .tag("a".to_owned());
+    let err: ConfigFactoryError = factory.finish().unwrap_err();
+    assert_eq!(err.to_string(), "missing field: port");
+
+    let config = factory.with_port(8080).finish().unwrap();
+    assert_eq!(config.host(), "localhost");
+    assert_eq!(config.port(), 8080);
+    assert_eq!(Config::builder(), "not a builder");
+
+    let query = Query::builder().set_table("users".to_owned()).done().unwrap();
+    assert_eq!(query.table, "users");
+}
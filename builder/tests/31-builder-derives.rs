@@ -0,0 +1,49 @@
+// #[builder(derive(...))] on the struct derives traits for the builder, so
+// that it can be cloned, debug printed or compared like any other value.
+//
+// Any other attribute can be put on the builder with
+// #[builder(struct_attr(...))], and on the builder's storage of a field with
+// #[builder(field_attr(...))] on that field.
+
+use derive_builder::Builder;
+use std::collections::HashSet;
+
+#[derive(Builder, Debug)]
+#[builder(
+    derive(Clone, Debug, PartialEq),
+    struct_attr(derive(Eq, Hash)),
+    struct_attr(must_use = "a builder does nothing until it is built")
+)]
+pub struct Config {
+    host: String,
+    #[builder(field_attr(doc = "Port to listen on."))]
+    port: u16,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+}
+
+#[derive(Builder)]
+#[builder(typestate, derive(Clone, Debug, PartialEq))]
+pub struct Query {
+    table: String,
+    limit: Option<usize>,
+}
+
+fn main() {
+    let mut builder = Config::builder();
+    builder.host("localhost".to_owned()).tag("a".to_owned());
+
+    let mut other = builder.clone();
+    assert_eq!(builder, other);
+    other.port(8080);
+    assert_ne!(builder, other);
+    assert!(format!("{:?}", other).contains("port: Some(8080)"));
+
+    let builders = HashSet::from([builder.clone(), builder, other]);
+    assert_eq!(builders.len(), 2);
+
+    let query = Query::builder().table("users".to_owned());
+    let copy = query.clone();
+    assert_eq!(query, copy);
+    assert_eq!(copy.limit(10).build().unwrap().limit, Some(10));
+}
@@ -0,0 +1,83 @@
+// Crates without the standard library can use the builder too. With
+// #[builder(no_std)] the generated code only refers to `core` and `alloc`,
+// which such crates are expected to link.
+//
+// The error type still implements `Display` and `core::error::Error`, and
+// reports missing fields as a `Vec<String>` from `alloc`.
+//
+// Reading fields from environment variables needs `std`, so `env` and
+// `env_prefix` are rejected in this mode.
+
+#![no_std]
+
+extern crate alloc;
+// Only linked to run the test; generated code cannot name it as `std`.
+extern crate std as host;
+
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(no_std, validate = "Sensor::check")]
+pub struct Sensor<'a> {
+    name: &'a str,
+    address: u8,
+    #[builder(each = "threshold")]
+    thresholds: Vec<u16>,
+    #[builder(default = "100")]
+    interval_ms: u32,
+    label: Option<String>,
+    #[builder(sub_builder)]
+    calibration: Calibration,
+}
+
+impl Sensor<'_> {
+    fn check(&self) -> Result<(), &'static str> {
+        match self.address {
+            0 => Err("address 0 is reserved"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Builder, Debug, Default, PartialEq)]
+#[builder(no_std, default)]
+pub struct Calibration {
+    offset: i16,
+    scale: u16,
+}
+
+#[derive(Builder, Debug)]
+#[builder(no_std, typestate)]
+pub struct Packet {
+    id: u16,
+    payload: Vec<u8>,
+}
+
+fn main() {
+    let mut builder = Sensor::builder();
+    builder.name("probe").threshold(10).threshold(20);
+    builder.calibration().scale(3);
+
+    let err = builder.build().err().unwrap();
+    assert_eq!(err, SensorBuilderError::MissingFields(["address".to_owned()].into()));
+    assert_eq!(err.to_string(), "missing field: address");
+
+    builder.address(0);
+    let err = builder.build().err().unwrap();
+    assert_eq!(err.to_string(), "address 0 is reserved");
+    let _: &dyn core::error::Error = &err;
+
+    let sensor = builder.address(0x40).build().unwrap();
+    assert_eq!(sensor.name, "probe");
+    assert_eq!(sensor.thresholds, [10, 20]);
+    assert_eq!(sensor.interval_ms, 100);
+    assert_eq!(sensor.label, None);
+    assert_eq!(sensor.calibration, Calibration { offset: 0, scale: 3 });
+
+    let packet = Packet::builder().id(7).payload([1, 2, 3].into()).build().unwrap();
+    assert_eq!(packet.id, 7);
+    assert_eq!(packet.payload, [1, 2, 3]);
+}
@@ -0,0 +1,64 @@
+// Fields marked #[builder(skip)] are left out of the builder: it has no setter
+// for them and `build()` fills them in with `Default::default()`.
+//
+// With #[builder(skip, default = "...")] the field is computed instead. The
+// expression runs once every other field is known and can refer to them by
+// name, so values derived from the others, such as a length or a cached hash,
+// are always consistent with them. That is the struct's name for each field,
+// even when #[builder(name = "...")] gives it another one in the builder.
+
+use derive_builder::Builder;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+#[derive(Builder, Debug)]
+pub struct Message {
+    topic: String,
+    #[builder(each = "part")]
+    parts: Vec<String>,
+    #[builder(skip, default = "parts.iter().map(String::len).sum()")]
+    len: usize,
+    #[builder(skip, default = "Message::hash(&topic, &parts)")]
+    checksum: u64,
+    #[builder(skip)]
+    retries: u32,
+}
+
+impl Message {
+    fn hash(topic: &str, parts: &[String]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (topic, parts).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[derive(Builder, Debug)]
+pub struct Origin {
+    #[builder(name = "hostname")]
+    host: String,
+    #[builder(skip, default = "host.len()")]
+    host_len: usize,
+}
+
+#[derive(Builder, Debug)]
+#[builder(typestate)]
+pub struct Span(u32, u32, #[builder(skip, default = "_1 - _0")] u32);
+
+fn main() {
+    let mut builder = Message::builder();
+    builder.part("hello".to_owned()).part(" world".to_owned());
+
+    let err = builder.build().err().unwrap();
+    assert_eq!(err, MessageBuilderError::MissingFields(vec!["topic".to_owned()]));
+
+    let message = builder.topic("greeting".to_owned()).build().unwrap();
+    assert_eq!(message.len, 11);
+    assert_eq!(message.checksum, Message::hash("greeting", &message.parts));
+    assert_eq!(message.retries, 0);
+
+    let origin = Origin::builder().hostname("example.com".to_owned()).build().unwrap();
+    assert_eq!(origin.host_len, 11);
+
+    let span = Span::builder()._0(3)._1(10).build().unwrap();
+    assert_eq!(span.2, 7);
+}
@@ -0,0 +1,81 @@
+// The builder can be inspected while it is being filled in, as a config
+// editor showing which fields are still outstanding would need:
+//
+//     fn <field>_ref(&self) -> Option<&T>
+//     fn is_<field>_set(&self) -> bool
+//     fn clear_<field>(&mut self)
+//     fn missing_fields(&self) -> Vec<&'static str>
+//
+// `missing_fields` lists the required fields that `build()` would report, in
+// declaration order. Fields with a default or an `Option` type are never
+// missing, and a field with a sub-builder is missing when its own builder
+// would fail to build.
+//
+// A typestate builder tracks its required fields in its type, so only its
+// optional fields can be cleared.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+pub struct Listener {
+    host: String,
+    port: u16,
+}
+
+#[derive(Builder, Debug)]
+pub struct Config {
+    name: String,
+    description: Option<String>,
+    #[builder(default = "4")]
+    workers: usize,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+    #[builder(sub_builder)]
+    listener: Listener,
+}
+
+#[derive(Builder, Debug)]
+#[builder(typestate)]
+pub struct Query {
+    table: String,
+    limit: Option<usize>,
+}
+
+fn main() {
+    let mut builder = Config::builder();
+    assert_eq!(builder.missing_fields(), ["name", "listener"]);
+    assert!(!builder.is_name_set());
+    assert_eq!(builder.name_ref(), None);
+
+    builder.name("api".to_owned()).description("public".to_owned());
+    assert!(builder.is_name_set());
+    assert_eq!(builder.name_ref().map(String::as_str), Some("api"));
+    assert_eq!(builder.description_ref().map(String::as_str), Some("public"));
+    assert_eq!(builder.workers_ref(), None);
+    assert_eq!(builder.missing_fields(), ["listener"]);
+
+    builder.tag("a".to_owned());
+    assert_eq!(builder.tags_ref(), Some(&vec!["a".to_owned()]));
+
+    builder.listener().host("localhost".to_owned());
+    assert_eq!(builder.missing_fields(), ["listener"]);
+    assert_eq!(builder.listener_ref().unwrap().missing_fields(), ["port"]);
+    builder.listener().port(8080);
+    assert!(builder.missing_fields().is_empty());
+    assert!(builder.build().is_ok());
+
+    builder.clear_name();
+    builder.clear_description();
+    assert!(!builder.is_name_set());
+    assert_eq!(builder.description_ref(), None);
+    assert_eq!(builder.missing_fields(), ["name"]);
+    assert!(builder.build().is_err());
+
+    let mut query = Query::builder().table("users".to_owned()).limit(10);
+    assert_eq!(query.limit_ref(), Some(&10));
+    query.clear_limit();
+    assert!(!query.is_limit_set());
+    assert!(query.is_table_set());
+    assert!(query.missing_fields().is_empty());
+    assert_eq!(query.build().unwrap().limit, None);
+}
@@ -0,0 +1,124 @@
+// #[builder] on a function gives it named and defaulted arguments. For
+//
+//     #[builder]
+//     fn connect(host: &str, port: u16, #[builder(default = 30)] timeout: u32)
+//
+// the macro keeps `connect` as it is and adds `connect_builder()`, returning
+// a builder with a setter per argument whose `call()` calls `connect` with
+// them. Arguments are classified as struct fields are, so `Option` arguments
+// may be left out and collections filled one item at a time with `each`.
+//
+// The builder is a typestate builder: `call()` only compiles once every
+// required argument is given, and returns whatever the function does.
+//
+// Methods get a builder too when both they and their impl block are marked
+// #[builder], as the builder has to be defined outside of the impl block.
+// `client.send_builder()` starts out holding the receiver.
+
+use derive_builder::builder;
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+#[builder]
+pub fn connect(host: &str, port: u16, #[builder(default = 30)] timeout: u32) -> String {
+    format!("{}:{} ({}s)", host, port, timeout)
+}
+
+#[builder(setter_prefix = "with_")]
+pub fn command(
+    program: &str,
+    #[builder(each = "arg", setter(into))] args: Vec<String>,
+    cwd: Option<&str>,
+) -> Vec<String> {
+    let mut line = vec![program.to_owned()];
+    line.extend(args);
+    line.extend(cwd.map(|cwd| format!("in {}", cwd)));
+    line
+}
+
+#[builder]
+pub fn parse<T: std::str::FromStr>(text: &str, #[builder(default)] trim: bool) -> Option<T> {
+    let text = if trim { text.trim() } else { text };
+    text.parse().ok()
+}
+
+#[builder]
+pub async fn fetch(path: String, #[builder(default = 3)] retries: u8) -> (String, u8) {
+    (path, retries)
+}
+
+pub struct Client<T> {
+    base: String,
+    sent: Vec<T>,
+}
+
+#[builder]
+impl<T: Clone> Client<T> {
+    #[builder]
+    pub fn new(#[builder(setter(into))] base: String) -> Self {
+        Client {
+            base,
+            sent: Vec::new(),
+        }
+    }
+
+    #[builder]
+    pub fn send(&mut self, path: &str, body: T, #[builder(default = 1)] attempts: u32) -> &str {
+        self.sent.push(body);
+        let _ = attempts;
+        &self.base[..path.len().min(self.base.len())]
+    }
+
+    #[builder]
+    pub fn describe(&self, verbose: Option<bool>) -> String {
+        match verbose {
+            Some(true) => format!("{} ({} sent)", self.base, self.sent.len()),
+            _ => self.base.clone(),
+        }
+    }
+
+    #[builder]
+    pub fn forward(&self, receiver: &str) -> String {
+        format!("{} -> {}", self.base, receiver)
+    }
+}
+
+fn main() {
+    let host = String::from("localhost");
+    let line = connect_builder().host(&host).port(8080).call();
+    assert_eq!(line, "localhost:8080 (30s)");
+    assert_eq!(line, connect("localhost", 8080, 30));
+    assert_eq!(connect_builder().port(1).timeout(5).host("db").call(), "db:1 (5s)");
+
+    let line = command_builder()
+        .with_program("ls")
+        .arg("-l")
+        .arg("-a")
+        .with_cwd("/tmp")
+        .call();
+    assert_eq!(line, ["ls", "-l", "-a", "in /tmp"]);
+
+    let n: Option<u32> = parse_builder().text(" 42 ").trim(true).call();
+    assert_eq!(n, Some(42));
+    assert_eq!(parse_builder::<u32>().text(" 42 ").call(), None);
+
+    let future = pin!(fetch_builder().path("/index".to_owned()).call());
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(future.poll(&mut cx), Poll::Ready(("/index".to_owned(), 3)));
+
+    let mut client = Client::new_builder().base("https://example.com").call();
+    let prefix = client.send_builder().path("https").body(7).call();
+    assert_eq!(prefix, "https");
+    client.send_builder().body(8).path("").attempts(2).call();
+    assert_eq!(client.sent, [7, 8]);
+    assert_eq!(client.describe_builder().call(), "https://example.com");
+    assert_eq!(
+        client.describe_builder().verbose(true).call(),
+        "https://example.com (2 sent)",
+    );
+    assert_eq!(
+        client.forward_builder().receiver("backup").call(),
+        "https://example.com -> backup",
+    );
+}
@@ -0,0 +1,41 @@
+// Calling a function through its builder without one of its required
+// arguments is a compile error naming that argument, as with a missing field
+// of a typestate builder.
+//
+// Functions whose arguments cannot be stored in a builder or depend on
+// #[cfg] are rejected, as are methods whose impl block is not marked
+// #[builder] and options that only make sense for structs.
+
+use derive_builder::builder;
+
+#[builder]
+pub fn connect(host: &str, port: u16) -> String {
+    format!("{}:{}", host, port)
+}
+
+#[builder]
+pub fn open(path: impl AsRef<str>, (read, write): (bool, bool)) {}
+
+#[builder]
+pub fn listen(#[cfg(unix)] socket: &str, port: u16) {}
+
+#[builder(validate = "check")]
+pub fn retry(attempts: u32) {}
+
+pub struct Client;
+
+impl Client {
+    #[builder]
+    pub fn send(&self, path: &str) {}
+}
+
+#[builder]
+impl Clone for Client {
+    fn clone(&self) -> Self {
+        Client
+    }
+}
+
+fn main() {
+    let _ = connect_builder().host("localhost").call();
+}
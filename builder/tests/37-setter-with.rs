@@ -0,0 +1,100 @@
+// #[builder(setter(with = ...))] preprocesses the value of a field as it is
+// set. With a closure, the setter takes the closure's arguments, which need
+// their types written out, and stores what it returns.
+//
+// A function can be given by path instead. A plain path transforms a value
+// of the field's type, while casting it to a function pointer type, as in
+// "parse_duration as fn(&str) -> Result<Duration, ParseIntError>", makes the
+// setter take that type's arguments.
+//
+// A function returning a `Result` needs #[builder(setter(fallible))], which
+// makes the setter return its error rather than the builder on its own.
+//
+// Under #[builder(env_prefix = "...")], fields with a `with` function are not
+// read from the environment, since the function's arguments need not be
+// parsed from a string.
+
+use derive_builder::Builder;
+use std::num::ParseIntError;
+use std::time::Duration;
+
+mod units {
+    use std::num::ParseIntError;
+    use std::time::Duration;
+
+    pub fn parse_duration(s: &str) -> Result<Duration, ParseIntError> {
+        match s.strip_suffix("ms") {
+            Some(ms) => Ok(Duration::from_millis(ms.parse()?)),
+            None => Ok(Duration::from_secs(s.trim_end_matches('s').parse()?)),
+        }
+    }
+}
+
+fn normalize(host: String) -> String {
+    host.to_lowercase()
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(setter(with = |s: &str| s.trim().to_owned()))]
+    name: String,
+    #[builder(setter(with = "normalize"))]
+    host: String,
+    #[builder(setter(with = |host: &str, port: u16| format!("{}:{}", host, port)))]
+    addr: String,
+    #[builder(setter(
+        with = "units::parse_duration as fn(&str) -> Result<Duration, ParseIntError>",
+        fallible
+    ))]
+    timeout: Option<Duration>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Retry {
+    #[builder(setter(with = "units::parse_duration as fn(&str) -> Result<Duration, ParseIntError>", fallible))]
+    delay: Duration,
+    #[builder(setter(with = |n: i64| n.clamp(0, 10) as u32))]
+    attempts: u32,
+}
+
+#[derive(Builder, Debug)]
+#[builder(env_prefix = "WITH_TEST_")]
+pub struct Endpoint {
+    port: u16,
+    #[builder(setter(with = normalize))]
+    host: String,
+}
+
+fn main() -> Result<(), ParseIntError> {
+    let server = Server::builder()
+        .name("  api  ")
+        .host("EXAMPLE.com".to_owned())
+        .addr("0.0.0.0", 8080)
+        .timeout("250ms")?
+        .build()
+        .unwrap();
+    assert_eq!(server.name, "api");
+    assert_eq!(server.host, "example.com");
+    assert_eq!(server.addr, "0.0.0.0:8080");
+    assert_eq!(server.timeout, Some(Duration::from_millis(250)));
+
+    assert!(Server::builder().timeout("soon").is_err());
+
+    let retry = Retry::builder().delay("2s")?.attempts(50).build().unwrap();
+    assert_eq!(retry.delay, Duration::from_secs(2));
+    assert_eq!(retry.attempts, 10);
+
+    std::env::set_var("WITH_TEST_PORT", "443");
+    std::env::set_var("WITH_TEST_HOST", "IGNORED");
+    let mut builder = EndpointBuilder::from_env().unwrap();
+    assert_eq!(
+        builder.build().unwrap_err(),
+        EndpointBuilderError::MissingFields(vec!["host".to_owned()]),
+    );
+    let endpoint = builder.host("API.example.com".to_owned()).build().unwrap();
+    assert_eq!(endpoint.port, 443);
+    assert_eq!(endpoint.host, "api.example.com");
+
+    Ok(())
+}
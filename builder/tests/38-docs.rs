@@ -0,0 +1,79 @@
+//! The builder is documented as pointing back to the type it builds, and every
+//! method it has gets docs, so that crates denying `missing_docs` can use it.
+//!
+//! Doc comments on a field are copied onto its setter. The `each` setter and
+//! `extend_<field>` say what they add to the field, followed by its docs.
+//!
+//! `#[deprecated]` on a field deprecates its setters too, without the builder
+//! itself using the field triggering the warning. `#[cfg(...)]` on a field
+//! applies to everything the builder generates for it.
+
+#![deny(missing_docs, deprecated)]
+
+use derive_builder::Builder;
+
+/// Settings of a server.
+#[derive(Builder)]
+pub struct Config {
+    /// Host to listen on.
+    #[builder(env = "CONFIG_HOST")]
+    host: String,
+    /// Port to listen on.
+    #[deprecated(note = "use `addr` instead")]
+    #[builder(default = "8080", setter(try_into))]
+    port: u16,
+    /// Tags of the server.
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+    #[cfg(not(any()))]
+    workers: Option<usize>,
+    #[cfg(any())]
+    threads: usize,
+}
+
+/// A query.
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Query {
+    /// Table to read.
+    #[deprecated]
+    table: String,
+    /// Most rows to read.
+    #[cfg(not(any()))]
+    limit: Option<usize>,
+}
+
+/// A shape.
+#[derive(Builder)]
+pub enum Shape {
+    /// A circle.
+    Circle {
+        /// Radius of the circle.
+        radius: f64,
+    },
+}
+
+/// Adds two numbers.
+#[derive_builder::builder]
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+fn main() {
+    let mut builder = Config::builder();
+    builder.host("localhost".to_owned()).tag("a".to_owned()).workers(4);
+    #[allow(deprecated)]
+    builder.port(80);
+    let config = builder.build().unwrap();
+    assert_eq!(config.workers, Some(4));
+    #[allow(deprecated)]
+    let port = config.port;
+    assert_eq!(port, 80);
+
+    #[allow(deprecated)]
+    let query = Query::builder().table("users".to_owned());
+    assert_eq!(query.limit(1).build().unwrap().limit, Some(1));
+
+    assert!(Shape::circle_builder().radius(1.0).build().is_ok());
+    assert_eq!(add_builder().a(1).b(2).call(), 3);
+}
@@ -0,0 +1,17 @@
+// A setter of a deprecated field is deprecated too, with the same note.
+
+#![deny(deprecated)]
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Config {
+    host: String,
+    #[deprecated(note = "use `addr` instead")]
+    #[builder(default = "8080")]
+    port: u16,
+}
+
+fn main() {
+    let _ = Config::builder().host("localhost".to_owned()).port(80).build();
+}
@@ -0,0 +1,45 @@
+// Methods of a builder that would end up with the same name are reported on
+// the later one, saying which method it clashes with and how to rename it,
+// rather than as a duplicate definition somewhere in the generated code.
+//
+// `each` on a field that is not a collection, options given more than once
+// and unknown options are errors too, with a suggestion for what was meant.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    arg: String,
+    build: bool,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Query {
+    table: String,
+    table_ref: Option<String>,
+    #[builder(each = "extend_tags")]
+    tags: Vec<String>,
+    extend_tags: Vec<String>,
+}
+
+#[derive(Builder)]
+pub struct Limits {
+    #[builder(each = "retry")]
+    retries: u32,
+    #[builder(default)]
+    #[builder(default = "1", setter(into, into))]
+    timeout: u64,
+    #[builder(optinal)]
+    port: Option<u16>,
+}
+
+#[derive(Builder)]
+#[builder(typestate, pattern = "owned", typestate, frobnicate)]
+pub struct Server {
+    host: String,
+}
+
+fn main() {}
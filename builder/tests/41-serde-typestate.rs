@@ -0,0 +1,12 @@
+// A typestate builder cannot be deserialized, as which of its fields are set
+// is part of its type.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate, deserialize)]
+pub struct Config {
+    host: String,
+}
+
+fn main() {}
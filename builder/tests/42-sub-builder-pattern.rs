@@ -0,0 +1,38 @@
+// The setters of a sub-builder are called through the `&mut` its field's
+// method returns, so only builders whose setters change them in place can be
+// one. Those of structs with #[builder(pattern = "owned")] or
+// #[builder(typestate)] take themselves by value, and those with
+// #[builder(pattern = "immutable")] return a copy that would be thrown away,
+// so a field's struct has to use the mutable pattern to be its sub-builder.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Clone)]
+#[builder(pattern = "owned")]
+pub struct Tls {
+    cert: String,
+}
+
+#[derive(Builder, Clone)]
+#[builder(typestate)]
+pub struct Auth {
+    user: String,
+}
+
+#[derive(Builder, Clone)]
+#[builder(pattern = "immutable")]
+pub struct Limits {
+    port: u16,
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(sub_builder)]
+    tls: Tls,
+    #[builder(sub_builder)]
+    auth: Auth,
+    #[builder(sub_builder)]
+    limits: Limits,
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+// A `with` function has to produce the field's type. One returning a `Result`
+// needs that return type written out, so that the setter knows to return its
+// error, and a plain path is always called with a value of the field's type,
+// so a function taking anything else has to be cast to its argument types.
+// Either mismatch is reported at `with` rather than inside the setter.
+
+use derive_builder::Builder;
+use std::time::Duration;
+
+fn parse_duration(s: &str) -> Duration {
+    Duration::from_secs(s.trim_end_matches('s').parse().unwrap_or(0))
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(setter(with = |s: &str| s.parse::<u16>()))]
+    port: u16,
+    #[builder(setter(with = "parse_duration"))]
+    timeout: Duration,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Pool {
+    #[builder(setter(with = |s: &str| s.parse::<u32>()))]
+    workers: u32,
+}
+
+fn main() {}
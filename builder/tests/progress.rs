@@ -10,4 +10,41 @@ fn tests() {
     t.pass("tests/07-repeated-field.rs");
     t.compile_fail("tests/08-unrecognized-attribute.rs");
     t.pass("tests/09-redefined-prelude-types.rs");
+    t.pass("tests/10-typestate.rs");
+    t.compile_fail("tests/11-typestate-missing-field.rs");
+    t.pass("tests/12-missing-fields-error.rs");
+    t.pass("tests/13-custom-error.rs");
+    t.pass("tests/14-generics.rs");
+    t.pass("tests/15-tuple-struct.rs");
+    t.pass("tests/16-enum.rs");
+    t.pass("tests/17-defaults.rs");
+    t.pass("tests/18-setter-conversions.rs");
+    t.pass("tests/19-builder-pattern.rs");
+    t.pass("tests/20-validate.rs");
+    t.pass("tests/21-each-collections.rs");
+    t.pass("tests/22-round-trip.rs");
+    t.pass("tests/23-wrapper-types.rs");
+    t.compile_fail("tests/24-field-attribute-errors.rs");
+    t.compile_fail("tests/25-struct-attribute-errors.rs");
+    t.pass("tests/26-sub-builder.rs");
+    t.pass("tests/27-merge.rs");
+    t.pass("tests/28-env.rs");
+    t.pass("tests/30-naming.rs");
+    t.pass("tests/31-builder-derives.rs");
+    t.pass("tests/32-no-std.rs");
+    t.pass("tests/33-skip.rs");
+    t.pass("tests/34-introspection.rs");
+    t.pass("tests/35-function-builder.rs");
+    t.compile_fail("tests/36-function-builder-errors.rs");
+    t.pass("tests/37-setter-with.rs");
+    t.pass("tests/38-docs.rs");
+    t.compile_fail("tests/39-deprecated-setter.rs");
+    t.compile_fail("tests/40-name-collisions.rs");
+    t.compile_fail("tests/42-sub-builder-pattern.rs");
+    t.compile_fail("tests/43-setter-with-errors.rs");
+    // These two need the `serde` feature: `cargo test --features serde`.
+    #[cfg(feature = "serde")]
+    t.pass("tests/29-serde.rs");
+    #[cfg(feature = "serde")]
+    t.compile_fail("tests/41-serde-typestate.rs");
 }